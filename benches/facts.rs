@@ -0,0 +1,124 @@
+use std::collections::HashSet;
+
+use criterion::{ criterion_group, criterion_main, BenchmarkId, Criterion };
+use expert_system::{ parser, Fact, Facts };
+
+/// `Facts` as they were before bitsets, three hash sets
+#[derive(Clone, Debug, Default, PartialEq)]
+struct HashFacts {
+    yes: HashSet<Fact>,
+    no: HashSet<Fact>,
+    unknown: HashSet<Fact>,
+}
+
+impl HashFacts {
+    fn from(facts: &Facts) -> HashFacts {
+        HashFacts {
+            yes: facts.yes.iter().collect(),
+            no: facts.no.iter().collect(),
+            unknown: facts.unknown.iter().collect(),
+        }
+    }
+
+    fn merge(&self, other: &HashFacts) -> Option<HashFacts> {
+        let yes: HashSet<Fact> = self.yes.iter().chain(other.yes.iter()).cloned().collect();
+        let no: HashSet<Fact> = self.no.iter().chain(other.no.iter()).cloned().collect();
+
+        for fact in yes.iter() {
+            if no.contains(fact) {
+                return None;
+            }
+        }
+
+        let unknown: HashSet<Fact> = self.unknown
+            .iter()
+            .chain(other.unknown.iter())
+            .filter(|fact| !yes.contains(fact) && !no.contains(fact))
+            .cloned()
+            .collect();
+
+        Some(HashFacts { yes, no, unknown })
+    }
+
+    fn invert(&self) -> HashFacts {
+        HashFacts { yes: self.no.clone(), no: self.yes.clone(), unknown: self.unknown.clone() }
+    }
+}
+
+/// Working facts of a search with `n` known facts and a few unknown ones,
+/// along with outcomes of rules concluding the unknown ones
+fn workload(n: usize) -> (Facts, Vec<Facts>) {
+    let known = (0..n).map(|idx| Fact::new(&format!("K{}", idx))).collect::<Vec<_>>();
+    let unknown = (0..8).map(|idx| Fact::new(&format!("U{}", idx))).collect::<Vec<_>>();
+    let (yes, no) = known.split_at(n / 2);
+
+    let outcomes = (0..8)
+        .flat_map(|idx| {
+            let rule = parser::rul(&format!("K0 => (U{} ^ !U{}) | K1", idx, (idx + 1) % 8)).unwrap();
+            rule.try_match(&Facts::new(&known, &[], &[])).unwrap().unwrap()
+        })
+        .collect();
+
+    (Facts::new(yes, no, &unknown), outcomes)
+}
+
+/// What `run` does with each outcome of a rule: merge it with working facts
+/// and see whether anything unknown got known
+fn branch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("branch");
+
+    for &n in [10, 100, 1000].iter() {
+        let (facts, outcomes) = workload(n);
+
+        group.bench_with_input(BenchmarkId::new("bitset", n), &(), |b, _| b.iter(|| {
+            outcomes
+                .iter()
+                .filter_map(|outcome| facts.merge(outcome))
+                .filter(|merged| merged.unknown.len() < facts.unknown.len())
+                .count()
+        }));
+
+        let (facts, outcomes) = (HashFacts::from(&facts), outcomes.iter().map(HashFacts::from).collect::<Vec<_>>());
+
+        group.bench_with_input(BenchmarkId::new("hashset", n), &(), |b, _| b.iter(|| {
+            outcomes
+                .iter()
+                .filter_map(|outcome| facts.merge(outcome))
+                .filter(|merged| merged.unknown.len() < facts.unknown.len())
+                .count()
+        }));
+    }
+
+    group.finish();
+}
+
+/// What `possible_combinations_recursive` does for `!(..)` and `(..) ^ (..)`
+fn combinations(c: &mut Criterion) {
+    let mut group = c.benchmark_group("combinations");
+
+    for &n in [10, 100, 1000].iter() {
+        let (facts, outcomes) = workload(n);
+        let outcomes = outcomes.iter().filter_map(|outcome| outcome.merge(&facts)).collect::<Vec<_>>();
+
+        group.bench_with_input(BenchmarkId::new("bitset", n), &(), |b, _| b.iter(|| {
+            outcomes
+                .iter()
+                .flat_map(|l| outcomes.iter().filter_map(move |r| l.merge(&r.invert())))
+                .count()
+        }));
+
+        let outcomes = outcomes.iter().map(HashFacts::from).collect::<Vec<_>>();
+
+        group.bench_with_input(BenchmarkId::new("hashset", n), &(), |b, _| b.iter(|| {
+            outcomes
+                .iter()
+                .flat_map(|l| outcomes.iter().filter_map(move |r| l.merge(&r.invert())))
+                .count()
+        }));
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, branch, combinations);
+criterion_main!(benches);
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+use criterion::{ criterion_group, criterion_main, BenchmarkId, Criterion };
+use expert_system::{ forward, parser, Fact, Facts, KnowledgeBase, Network, Rule };
+
+/// Add chain `F0 => F1 => ... => Fn` with `F0` given, its rules added from the end,
+/// so that a pass over rules in id order fires only one of them
+fn chain(kb: &mut KnowledgeBase, n: usize) {
+    for idx in (0..n).rev() {
+        kb.add_rule(parser::rul(&format!("F{} => F{}", idx, idx + 1)).unwrap()).unwrap();
+    }
+
+    kb.set_given(Facts::new(&[Fact::new("F0")], &[], &[])).unwrap();
+}
+
+/// Chain `F0 => ... => F50` with `F0` given, after `n` rules that have nothing to do with it,
+/// so that looking through rules for the chain goes past all of them
+fn haystack(n: usize) -> KnowledgeBase {
+    let mut kb = KnowledgeBase::new();
+
+    for idx in 0..n {
+        kb.add_rule(parser::rul(&format!("N{0} + O{0} => M{0} | P{0}", idx)).unwrap()).unwrap();
+    }
+
+    chain(&mut kb, 50);
+    kb
+}
+
+fn network_of(kb: &KnowledgeBase) -> Network {
+    Network::new(kb.rules().values().cloned().collect())
+}
+
+/// Forward chaining that fires every rule in turn until none of them sets anything,
+/// the way it's done without going back only to rules mentioning changed facts
+fn derive_by_scan(network: &Network, given: &Facts) -> Facts {
+    let mut facts = given.clone();
+    let mut steps = HashMap::new();
+
+    loop {
+        let mut changed = false;
+
+        for idx in 0..network.rules().len() {
+            let forced = forward::fire(network, idx, &mut facts, &mut steps).unwrap();
+            changed |= !forced.is_empty(true, true, false);
+        }
+
+        if !changed {
+            return facts;
+        }
+    }
+}
+
+/// Backward chaining that looks through every rule for the ones concluding `goal`,
+/// the way it's done without a `Network`. It skips the bookkeeping of the real search,
+/// so it's faster than a search going through every rule would be
+fn query_by_scan(rules: &[Rule], goal: Fact, facts: &mut Facts) {
+    for rule in rules.iter() {
+        if facts.is_yes(goal) || facts.is_no(goal) {
+            return;
+        }
+
+        let (l, r) = match rule {
+            Rule::IfThen(ref l, ref r) => (l, r),
+            _ => continue,
+        };
+
+        if !r.iter_facts().any(|fact| fact == goal) {
+            continue;
+        }
+
+        for fact in l.iter_facts() {
+            if !facts.is_yes(fact) && !facts.is_no(fact) {
+                query_by_scan(rules, fact, facts);
+            }
+        }
+
+        if let Ok(Some(outcomes)) = rule.try_match(facts) {
+            if let [ref outcome] = outcomes[..] {
+                if let Some(merged) = facts.merge(outcome) {
+                    *facts = merged;
+                }
+            }
+        }
+    }
+}
+
+fn derive(c: &mut Criterion) {
+    let mut group = c.benchmark_group("derive");
+
+    for &n in [50, 100, 200].iter() {
+        let mut kb = KnowledgeBase::new();
+        chain(&mut kb, n);
+        let network = network_of(&kb);
+
+        group.bench_with_input(BenchmarkId::new("network", n), &network, |b, network| {
+            b.iter(|| forward::saturate(network, kb.given()).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("scan", n), &network, |b, network| {
+            b.iter(|| derive_by_scan(network, kb.given()))
+        });
+    }
+
+    group.finish();
+}
+
+fn query(c: &mut Criterion) {
+    let mut group = c.benchmark_group("query");
+
+    for &n in [100, 1000, 5000].iter() {
+        let kb = haystack(n);
+        let find = Facts::new(&[], &[], &[Fact::new("F50")]);
+        let rules = kb.rules().values().cloned().collect::<Vec<_>>();
+
+        group.bench_with_input(BenchmarkId::new("network", n), &kb, |b, kb| b.iter(|| kb.query(&find).unwrap()));
+        group.bench_with_input(BenchmarkId::new("scan", n), &rules, |b, rules| {
+            b.iter(|| {
+                let mut facts = kb.given().merge(&find).unwrap();
+                query_by_scan(rules, Fact::new("F50"), &mut facts);
+                facts
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn network(c: &mut Criterion) {
+    let kb = haystack(5000);
+    let rules = kb.rules().values().cloned().collect::<Vec<_>>();
+
+    c.bench_function("network 5050 rules", |b| b.iter(|| Network::new(rules.clone())));
+}
+
+criterion_group!(benches, derive, query, network);
+criterion_main!(benches);
@@ -0,0 +1,41 @@
+use super::{ Fact, FactSet };
+
+/// Value of every fact, facts that weren't set are false
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Assignment {
+    yes: FactSet,
+}
+
+impl Assignment {
+    pub fn new() -> Assignment {
+        Assignment::default()
+    }
+
+    /// Assignment where `facts[i]` has the value of bit `i` of `bits`,
+    /// or `None` if there are more facts than bits
+    pub fn from_bits(facts: &[Fact], bits: u64) -> Option<Assignment> {
+        if facts.len() > u64::BITS as usize {
+            return None;
+        }
+
+        let mut assignment = Assignment::new();
+
+        for (idx, &fact) in facts.iter().enumerate() {
+            assignment.set(fact, bits & 1 << idx != 0);
+        }
+
+        Some(assignment)
+    }
+
+    pub fn set(&mut self, fact: Fact, value: bool) {
+        if value {
+            self.yes.insert(fact);
+        } else {
+            self.yes.remove(fact);
+        }
+    }
+
+    pub fn get(&self, fact: Fact) -> bool {
+        self.yes.contains(fact)
+    }
+}
@@ -0,0 +1,13 @@
+use super::{ Fact, FactInfo };
+
+/// Source of answers about facts no rule can derive
+pub trait Consultant {
+    /// Value of `fact` described by `info`, or `None` if it's unknown
+    fn ask(&mut self, fact: Fact, info: Option<&FactInfo>) -> Option<bool>;
+}
+
+impl<F: FnMut(Fact, Option<&FactInfo>) -> Option<bool>> Consultant for F {
+    fn ask(&mut self, fact: Fact, info: Option<&FactInfo>) -> Option<bool> {
+        self(fact, info)
+    }
+}
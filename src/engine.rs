@@ -0,0 +1,723 @@
+use std::collections::{ BTreeMap, HashMap };
+use std::fmt;
+use std::fs;
+use std::sync::{ Arc, OnceLock };
+use std::time::{ Duration, Instant };
+
+use super::{ Error, Fact, FactInfo, Facts, Graph, Rule };
+use super::consult::Consultant;
+use super::forward;
+use super::network::Network;
+use super::oracle::TruthTable;
+use super::error::Limit;
+use super::proof::{ Proof, Step };
+use super::sat::Cnf;
+use super::verdict::{ Reason, Truth, Verdict };
+use super::trace::{ Event, Observer };
+
+/// Result of a query along with the way every fact got its value
+#[derive(Clone, Debug, PartialEq)]
+pub struct Answer {
+    pub facts: Facts,
+    pub steps: HashMap<Fact, Step>,
+}
+
+impl Answer {
+    /// Derivation tree of `fact`, if it was established
+    pub fn explain(&self, fact: Fact) -> Option<Proof> {
+        Proof::build(fact, &self.facts, &self.steps)
+    }
+
+    /// Truth value of `fact` and the reason behind it
+    pub fn verdict(&self, fact: Fact) -> Verdict {
+        let proof = match self.explain(fact) {
+            Some(proof) => proof,
+            None => return Verdict { fact, truth: Truth::False, reason: Reason::Default },
+        };
+
+        if let Some(Step::Rule { rule, .. }) = proof.first_assumption() {
+            return Verdict { fact, truth: Truth::Undetermined, reason: Reason::Ambiguous(rule.clone()) };
+        }
+
+        let truth = if proof.value { Truth::True } else { Truth::False };
+        let reason = match proof.step {
+            Step::Given => Reason::Given,
+            Step::Answered => Reason::Answered,
+            Step::Rule { rule, .. } => Reason::Derived(rule),
+        };
+
+        Verdict { fact, truth, reason }
+    }
+
+    /// Verdicts on every fact `find` asks about, sorted by name
+    pub fn verdicts(&self, find: &Facts) -> Vec<Verdict> {
+        let mut keys = find.yes.iter().chain(find.no.iter()).chain(find.unknown.iter()).collect::<Vec<_>>();
+        keys.sort_by_key(|fact| fact.name());
+
+        keys.iter().map(|&fact| self.verdict(fact)).collect()
+    }
+}
+
+/// Bounds on the search done by a single query
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limits {
+    /// Deepest recursion level
+    pub depth: usize,
+    /// Most search levels to enter overall
+    pub steps: Option<usize>,
+    /// Longest time to search
+    pub time: Option<Duration>,
+}
+
+impl Limits {
+    /// Change one of the limits, zero step or time limit means no limit
+    pub fn set(&mut self, limit: Limit) {
+        match limit {
+            Limit::Depth(depth) => self.depth = depth,
+            Limit::Steps(steps) => self.steps = if steps == 0 { None } else { Some(steps) },
+            Limit::Time(time) => self.time = if time == Duration::from_secs(0) { None } else { Some(time) },
+        }
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits { depth: 100, steps: None, time: None }
+    }
+}
+
+/// Rules and given facts that can't all hold together
+#[derive(Clone, Debug, PartialEq)]
+pub struct Conflict {
+    pub rules: Vec<Rule>,
+    pub facts: Facts,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for rule in self.rules.iter() {
+            writeln!(f, "{}", rule)?;
+        }
+
+        if !self.facts.is_empty(true, true, false) {
+            writeln!(f, "given {}", self.facts)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// How queries are answered
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Mode {
+    /// Backtracking search through rules, traced and explainable
+    #[default]
+    Search,
+    /// Exact entailment check with a SAT solver
+    Exact,
+}
+
+/// Rule set together with initial facts, able to answer queries
+#[derive(Clone, Debug, Default)]
+pub struct KnowledgeBase {
+    rules: BTreeMap<usize, Rule>,
+    /// Id of every rule, so that adding a rule twice is noticed
+    ids: HashMap<Rule, usize>,
+    /// Last id given out, ids of removed rules aren't reused
+    last_id: usize,
+    given: Facts,
+    constraints: Vec<Rule>,
+    info: HashMap<Fact, FactInfo>,
+    limits: Limits,
+    mode: Mode,
+    /// Index of rules and constraints, built on first use after they change
+    /// and shared by clones of the knowledge base
+    network: OnceLock<Arc<Network>>,
+    /// Whether rules and constraints hold together with given facts,
+    /// found on the first query after any of them changes
+    consistent: OnceLock<bool>,
+}
+
+impl KnowledgeBase {
+    pub fn new() -> KnowledgeBase {
+        KnowledgeBase::default()
+    }
+
+    /// Rules by their ids, an id stays the same for as long as the rule is there
+    pub fn rules(&self) -> &BTreeMap<usize, Rule> {
+        &self.rules
+    }
+
+    pub fn given(&self) -> &Facts {
+        &self.given
+    }
+
+    pub fn constraints(&self) -> &[Rule] {
+        &self.constraints
+    }
+
+    /// Descriptions and question texts of facts
+    pub fn info(&self) -> &HashMap<Fact, FactInfo> {
+        &self.info
+    }
+
+    /// Set description and question text of `fact`
+    pub fn describe(&mut self, fact: Fact, info: FactInfo) {
+        self.info.insert(fact, info);
+    }
+
+    /// Add `rule` to the rule set, returning its id or `None` if it was already there.
+    /// Expressions without `=>` or `<=>` are refused, they go to constraints instead
+    pub fn add_rule(&mut self, rule: Rule) -> Result<Option<usize>, Error> {
+        if !rule.is_rule() {
+            return Err(Error::NotARule(rule));
+        }
+
+        if self.ids.contains_key(&rule) {
+            return Ok(None);
+        }
+
+        self.network = OnceLock::new();
+        self.consistent = OnceLock::new();
+        self.last_id += 1;
+        self.ids.insert(rule.clone(), self.last_id);
+        self.rules.insert(self.last_id, rule);
+
+        Ok(Some(self.last_id))
+    }
+
+    /// Remove `rule` from the rule set, returning `false` if it wasn't there
+    pub fn remove_rule(&mut self, rule: &Rule) -> bool {
+        match self.ids.get(rule) {
+            Some(&id) => self.remove_rule_by_id(id).is_some(),
+            None => false,
+        }
+    }
+
+    /// Remove rule with `id` from the rule set, returning it
+    pub fn remove_rule_by_id(&mut self, id: usize) -> Option<Rule> {
+        let rule = self.rules.remove(&id)?;
+
+        self.network = OnceLock::new();
+        self.consistent = OnceLock::new();
+        self.ids.remove(&rule);
+
+        Some(rule)
+    }
+
+    /// Ids of rules equivalent to `rule`, like `B + A => C` and `!!A + B => C` are to `A + B => C`
+    pub fn lookup(&self, rule: &Rule) -> Vec<usize> {
+        let normalized = rule.normalized();
+
+        self.rules
+            .iter()
+            .filter(|(_, other)| other.normalized() == normalized)
+            .map(|(&id, _)| id)
+            .collect()
+    }
+
+    /// Remove every rule equivalent to `rule`, returning them with their ids
+    pub fn remove_equivalent(&mut self, rule: &Rule) -> Vec<(usize, Rule)> {
+        self.lookup(rule)
+            .into_iter()
+            .filter_map(|id| self.remove_rule_by_id(id).map(|rule| (id, rule)))
+            .collect()
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+    }
+
+    /// Replace initial facts, failing if some fact is given both true and false
+    pub fn set_given(&mut self, given: Facts) -> Result<(), Error> {
+        if !given.yes.is_disjoint(&given.no) {
+            return Err(Error::Contradiction);
+        }
+
+        self.consistent = OnceLock::new();
+        self.given = given;
+        Ok(())
+    }
+
+    /// Replace expressions that hold initially, like `X ^ Y`
+    pub fn set_constraints(&mut self, constraints: Vec<Rule>) {
+        self.network = OnceLock::new();
+        self.consistent = OnceLock::new();
+        self.constraints = constraints;
+    }
+
+    /// Find out facts listed as unknown in `find`, starting from given facts
+    pub fn query(&self, find: &Facts) -> Result<Facts, Error> {
+        self.query_with(find, &mut ())
+    }
+
+    /// Same as `query`, reporting every reasoning step to `observer`
+    pub fn query_with(&self, find: &Facts, observer: &mut dyn Observer) -> Result<Facts, Error> {
+        self.solve(find, observer).map(|answer| answer.facts)
+    }
+
+    /// Same as `query_with`, also recording how every fact was established
+    pub fn solve(&self, find: &Facts, observer: &mut dyn Observer) -> Result<Answer, Error> {
+        self.search(find, observer, None)
+    }
+
+    /// Same as `solve`, asking `consultant` about every needed fact that no rule concludes
+    pub fn consult(
+        &self,
+        find: &Facts,
+        observer: &mut dyn Observer,
+        consultant: &mut dyn Consultant
+    ) -> Result<Answer, Error> {
+        self.search(find, observer, Some(consultant))
+    }
+
+    fn search(
+        &self,
+        find: &Facts,
+        observer: &mut dyn Observer,
+        consultant: Option<&mut dyn Consultant>
+    ) -> Result<Answer, Error> {
+        let facts = self.given.merge(find).ok_or(Error::Contradiction)?;
+
+        let started = Instant::now();
+
+        // Search only follows rules leading to the goal, so it wouldn't notice the others
+        // or constraints contradicting facts
+        let consistent = if !find.is_empty(true, true, false) {
+            self.is_satisfiable(&facts, started)?
+        } else if let Some(&consistent) = self.consistent.get() {
+            consistent
+        } else {
+            let consistent = self.is_satisfiable(&self.given, started)?;
+            let _ = self.consistent.set(consistent);
+            consistent
+        };
+
+        if !consistent {
+            return Err(Error::Contradiction);
+        }
+
+        let steps = facts.yes.iter().chain(facts.no.iter()).map(|fact| (fact, Step::Given)).collect();
+
+        let mut solver = Solver {
+            network: self.network(),
+            observer,
+            consultant: consultant.map(|consultant| consultant as &mut dyn Consultant),
+            info: &self.info,
+            answers: HashMap::new(),
+            limits: self.limits,
+            visited: 0,
+            started,
+        };
+        let (facts, steps) = solver.run(HashMap::new(), Vec::new(), facts, steps, 0)?;
+
+        Ok(Answer { facts, steps })
+    }
+
+    /// Verdicts on facts `find` asks about in the current mode,
+    /// `observer` only hears from the search mode
+    pub fn ask(&self, find: &Facts, observer: &mut dyn Observer) -> Result<Vec<Verdict>, Error> {
+        match self.mode {
+            Mode::Search => self.solve(find, observer).map(|answer| answer.verdicts(find)),
+            Mode::Exact => self.entailed(find),
+        }
+    }
+
+    /// Verdicts on facts `find` asks about, true or false only if every model
+    /// of the rules and given facts agrees on it
+    pub fn entailed(&self, find: &Facts) -> Result<Vec<Verdict>, Error> {
+        let mut cnf = Cnf::from_rules(self.rules.values().chain(self.constraints.iter()), &self.given);
+
+        if !cnf.is_satisfiable() {
+            return Err(Error::Contradiction);
+        }
+
+        let mut keys = find.yes.iter().chain(find.no.iter()).chain(find.unknown.iter()).collect::<Vec<_>>();
+        keys.sort_by_key(|fact| fact.name());
+
+        let verdicts = keys
+            .iter()
+            .map(|&fact| {
+                let lit = cnf.fact(fact);
+
+                let (truth, reason) = if cnf.entails(lit) {
+                    (Truth::True, if self.given.is_yes(fact) { Reason::Given } else { Reason::Entailed })
+                } else if cnf.entails(!lit) {
+                    (Truth::False, if self.given.is_no(fact) { Reason::Given } else { Reason::Entailed })
+                } else {
+                    (Truth::Undetermined, Reason::Independent)
+                };
+
+                Verdict { fact, truth, reason }
+            })
+            .collect();
+
+        Ok(verdicts)
+    }
+
+    /// Dependency graph of the rules, printed in DOT format
+    pub fn graph(&self) -> Graph<'_> {
+        Graph::new(&self.rules)
+    }
+
+    /// Every assignment satisfying rules, constraints and given facts, tried one by one,
+    /// or `None` if they mention more than `TruthTable::MAX_FACTS` facts
+    pub fn truth_table(&self) -> Option<TruthTable> {
+        TruthTable::new(self.rules.values().chain(self.constraints.iter()), &self.given)
+    }
+
+    /// Every fact that follows from given facts by firing rules forward
+    pub fn derive(&self) -> Result<Answer, Error> {
+        let (facts, steps) = forward::saturate(self.network(), &self.given)?;
+
+        Ok(Answer { facts, steps })
+    }
+
+    /// Rules as `IfThen` rules, followed by constraints
+    fn implications(&self) -> Vec<Rule> {
+        self.rules
+            .values()
+            // `add_rule` lets only rules in, so none of them fails
+            .flat_map(|rule| rule.implications().into_iter().flatten())
+            .chain(self.constraints.iter().cloned())
+            .collect()
+    }
+
+    /// Whether rules and constraints can all hold along with true and false `facts`,
+    /// within the time and step limits of a query started at `started`
+    fn is_satisfiable(&self, facts: &Facts, started: Instant) -> Result<bool, Error> {
+        Cnf::from_rules(self.rules.values().chain(self.constraints.iter()), facts)
+            .is_satisfiable_within(&self.limits, started)
+    }
+
+    fn network(&self) -> &Network {
+        self.network.get_or_init(|| Arc::new(Network::new(self.implications())))
+    }
+
+    /// Minimal set of rules and given facts that contradict each other,
+    /// or `None` if the knowledge base is consistent
+    pub fn check(&self) -> Option<Conflict> {
+        let mut cnf = Cnf::new();
+        let mut guards = Vec::new();
+
+        for rule in self.rules.values().chain(self.constraints.iter()) {
+            let guard = cnf.fresh();
+            cnf.add_rule_guarded(rule, guard);
+            guards.push((guard, Some(rule), None));
+        }
+
+        let given = self.given.yes.iter().map(|fact| (fact, true))
+            .chain(self.given.no.iter().map(|fact| (fact, false)));
+
+        for (fact, value) in given {
+            let (guard, lit) = (cnf.fresh(), cnf.fact(fact));
+            cnf.add_clause(vec![!guard, if value { lit } else { !lit }]);
+            guards.push((guard, None, Some((fact, value))));
+        }
+
+        let core = cnf.unsat_core(&guards.iter().map(|&(guard, _, _)| guard).collect::<Vec<_>>())?;
+
+        let mut conflict = Conflict { rules: Vec::new(), facts: Facts::default() };
+        for &(_, rule, fact) in guards.iter().filter(|(guard, _, _)| core.contains(guard)) {
+            if let Some(rule) = rule {
+                conflict.rules.push(rule.clone());
+            }
+
+            match fact {
+                Some((fact, true)) => { conflict.facts.yes.insert(fact); }
+                Some((fact, false)) => { conflict.facts.no.insert(fact); }
+                None => {}
+            }
+        }
+
+        Some(conflict)
+    }
+
+    /// Derivation tree of `fact`, or `None` if it can't be established
+    pub fn explain(&self, fact: Fact) -> Result<Option<Proof>, Error> {
+        let find = Facts::new(&[], &[], &[fact]);
+
+        self.solve(&find, &mut ()).map(|answer| answer.explain(fact))
+    }
+
+    /// Write the knowledge base to `path` in the input syntax
+    pub fn save(&self, path: &str) -> Result<(), Error> {
+        fs::write(path, self.to_string()).map_err(|cause| Error::Io { path: path.to_string(), cause: Arc::new(cause) })
+    }
+}
+
+/// Knowledge base in the input syntax, running it line by line builds the same knowledge base
+impl fmt::Display for KnowledgeBase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut described = self.info.iter().collect::<Vec<_>>();
+        described.sort_by_key(|&(fact, _)| fact.name());
+
+        for (fact, info) in described {
+            if info == &FactInfo::default() {
+                writeln!(f, "fact {}", fact)?;
+            } else {
+                writeln!(f, "fact {} {}", fact, info)?;
+            }
+        }
+
+        for rule in self.rules.values() {
+            writeln!(f, "{}", rule)?;
+        }
+
+        if !self.given.is_empty(true, true, false) || !self.constraints.is_empty() {
+            let mut given = self.given.yes
+                .iter()
+                .map(|fact| (fact, ""))
+                .chain(self.given.no.iter().map(|fact| (fact, "!")))
+                .collect::<Vec<_>>();
+            given.sort_by_key(|&(fact, _)| fact.name());
+
+            write!(f, "=")?;
+            for (idx, (fact, prefix)) in given.into_iter().enumerate() {
+                write!(f, "{}{}{}", if idx == 0 { "" } else { " " }, prefix, fact)?;
+            }
+
+            for (idx, constraint) in self.constraints.iter().enumerate() {
+                let space = if idx == 0 && self.given.is_empty(true, true, false) { "" } else { " " };
+
+                write!(f, "{}({})", space, constraint)?;
+            }
+
+            writeln!(f)?;
+        }
+
+        let default = Limits::default();
+
+        if self.limits.depth != default.depth {
+            writeln!(f, "limit depth {}", self.limits.depth)?;
+        }
+
+        if let Some(steps) = self.limits.steps {
+            writeln!(f, "limit steps {}", steps)?;
+        }
+
+        if let Some(time) = self.limits.time {
+            writeln!(f, "limit time {}", time.as_millis())?;
+        }
+
+        if self.mode == Mode::Exact {
+            writeln!(f, "mode exact")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Search state shared by all levels of a single query
+struct Solver<'a> {
+    /// Rules of the knowledge base as `IfThen` rules, followed by constraints
+    network: &'a Network,
+    observer: &'a mut dyn Observer,
+    consultant: Option<&'a mut dyn Consultant>,
+    info: &'a HashMap<Fact, FactInfo>,
+    /// Consultant's answers so far, it isn't asked twice about the same fact
+    answers: HashMap<Fact, Option<bool>>,
+    limits: Limits,
+    visited: usize,
+    started: Instant,
+}
+
+impl<'a> Solver<'a> {
+    fn run(
+        &mut self,
+        used_rules: HashMap<usize, bool>,
+        goals: Vec<Fact>,
+        mut facts: Facts,
+        mut steps: HashMap<Fact, Step>,
+        level: usize
+    ) -> Result<(Facts, HashMap<Fact, Step>), Error> {
+        self.check_limits(level)?;
+        self.consult(&mut facts, &mut steps, level);
+
+        self.observer.event(&Event::Entered { depth: level, facts: &facts });
+
+        if facts.is_empty(false, false, true) {
+            self.observer.event(&Event::Resolved { depth: level, facts: &facts });
+            return Ok((facts, steps));
+        }
+
+        let network = self.network;
+
+        // Unknown facts only get fewer below, so rules concluding none of them stay useless
+        for idx in network.concluding(facts.unknown.iter()) {
+            let rule = network.rule(idx);
+            // Constraints hold unconditionally, as if their premise was always true
+            let (l_facts, r_facts) = (network.premise(idx), network.conclusion(idx));
+
+            if used_rules.get(&idx) == Some(&true) {
+                continue;
+            }
+
+            if r_facts.iter().any(|&fact| facts.is_unknown(fact)) {
+                self.observer.event(&Event::RuleTried { depth: level, rule, facts: &facts });
+
+                let outcomes = match rule {
+                    Rule::IfThen(..) => rule.try_match(&facts)?,
+                    constraint => Some(constraint.possible_combinations_recursive()),
+                };
+
+                if let Some(outcomes) = outcomes {
+                    self.observer.event(&Event::Outcomes { depth: level, rule, outcomes: &outcomes });
+
+                    for outcome in outcomes.iter() {
+                        self.observer.event(&Event::OutcomeTried { depth: level, rule, outcome });
+
+                        if let Some(merged_facts) = facts.merge(outcome) {
+                            if merged_facts.unknown.len() == facts.unknown.len() {
+                                self.observer.event(&Event::NoProgress { depth: level, rule, outcome });
+                                continue;
+                            }
+
+                            let mut used_rules = used_rules.clone();
+                            used_rules.insert(idx, true);
+
+                            let premises = network.step_premises(idx, &facts);
+
+                            let mut new_steps = steps.clone();
+                            for fact in outcome.yes.iter().chain(outcome.no.iter()) {
+                                if !facts.is_yes(fact) && !facts.is_no(fact) {
+                                    let value = outcome.is_yes(fact);
+                                    let forced = outcomes
+                                        .iter()
+                                        .filter_map(|other| facts.merge(other))
+                                        .all(|other| if value { other.is_yes(fact) } else { other.is_no(fact) });
+
+                                    new_steps.insert(fact, Step::Rule {
+                                        rule: rule.clone(),
+                                        premises: premises.clone(),
+                                        outcome: outcome.clone(),
+                                        forced,
+                                    });
+                                }
+                            }
+
+                            let num_unknown = facts.unknown.len();
+                            let (new_facts, new_steps) =
+                                self.run(used_rules, goals.clone(), merged_facts, new_steps, level + 1)?;
+                            if new_facts.unknown.is_empty() {
+                                return Ok((new_facts, new_steps));
+                            } else if new_facts.unknown.len() < num_unknown {
+                                facts = new_facts;
+                                steps = new_steps;
+                            }
+                        } else {
+                            self.observer.event(&Event::Conflict { depth: level, rule, outcome });
+                        }
+                    }
+                } else {
+                    if used_rules.get(&idx) == Some(&false) {
+                        self.observer.event(&Event::NoMatch { depth: level, rule, facts: &facts });
+                        continue;
+                    }
+
+                    // Premise that is already pursued further up would lead back here
+                    if l_facts.iter().any(|fact| goals.contains(fact) || r_facts.contains(fact)) {
+                        self.observer.event(&Event::Cycle { depth: level, rule, facts: &facts });
+                        continue;
+                    }
+
+                    let mut used_rules = used_rules.clone();
+                    used_rules.insert(idx, false);
+
+                    let mut goals = goals.clone();
+                    goals.extend(r_facts.iter().filter(|&&fact| facts.is_unknown(fact)));
+
+                    let mut facts = facts.clone();
+                    for l_fact in l_facts.iter() {
+                        if !facts.is_yes(*l_fact) && !facts.is_no(*l_fact) {
+                            facts.unknown.insert(*l_fact);
+                        }
+                    }
+
+                    let num_unknown = facts.unknown.len();
+                    let (new_facts, new_steps) =
+                        self.run(used_rules, goals, facts, steps.clone(), level + 1)?;
+                    if new_facts.unknown.is_empty() {
+                        return Ok((new_facts, new_steps));
+                    } else if new_facts.unknown.len() < num_unknown {
+                        facts = new_facts;
+                    }
+                }
+            }
+        }
+
+        Ok((facts, steps))
+    }
+
+    /// Fill in unknown facts no rule concludes with consultant's answers
+    fn consult(&mut self, facts: &mut Facts, steps: &mut HashMap<Fact, Step>, level: usize) {
+        let consultant = match self.consultant {
+            Some(ref mut consultant) => consultant,
+            None => return,
+        };
+
+        let network = self.network;
+        let mut leaves = facts.unknown
+            .iter()
+            .filter(|&fact| !network.is_derivable(fact))
+            .collect::<Vec<_>>();
+        leaves.sort_by_key(|fact| fact.name());
+
+        for fact in leaves {
+            let answer = match self.answers.get(&fact) {
+                Some(&answer) => answer,
+                None => {
+                    let answer = consultant.ask(fact, self.info.get(&fact));
+                    self.answers.insert(fact, answer);
+                    self.observer.event(&Event::Asked { depth: level, fact, answer });
+                    answer
+                }
+            };
+
+            if let Some(value) = answer {
+                facts.unknown.remove(fact);
+                if value {
+                    facts.yes.insert(fact);
+                } else {
+                    facts.no.insert(fact);
+                }
+
+                steps.insert(fact, Step::Answered);
+            }
+        }
+    }
+
+    fn check_limits(&mut self, level: usize) -> Result<(), Error> {
+        self.visited += 1;
+
+        if level > self.limits.depth {
+            return Err(Error::LimitExceeded(Limit::Depth(self.limits.depth)));
+        }
+
+        if let Some(steps) = self.limits.steps {
+            if self.visited > steps {
+                return Err(Error::LimitExceeded(Limit::Steps(steps)));
+            }
+        }
+
+        if let Some(time) = self.limits.time {
+            if self.started.elapsed() > time {
+                return Err(Error::LimitExceeded(Limit::Time(time)));
+            }
+        }
+
+        Ok(())
+    }
+}
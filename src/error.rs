@@ -0,0 +1,90 @@
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::Rule;
+
+/// Bound on the search that was hit
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Limit {
+    Depth(usize),
+    Steps(usize),
+    Time(Duration),
+}
+
+/// Byte range of a line of input
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Clone, Debug)]
+pub enum Error {
+    /// Line doesn't follow the input syntax, `span` covers the part that can't be parsed
+    Parse { span: Span, expected: String },
+    /// Given facts contradict each other or the query
+    Contradiction,
+    /// Expression without `=>` or `<=>` was used where a rule is needed
+    NotARule(Rule),
+    /// Search was stopped before finishing
+    LimitExceeded(Limit),
+    /// File couldn't be read or written
+    Io { path: String, cause: Arc<io::Error> },
+}
+
+/// `io::Error` can't be compared, so `Io` errors are equal if their paths and error kinds are
+impl PartialEq for Error {
+    fn eq(&self, other: &Error) -> bool {
+        match (self, other) {
+            (Error::Parse { span, expected }, Error::Parse { span: other_span, expected: other_expected }) => {
+                span == other_span && expected == other_expected
+            }
+            (Error::Contradiction, Error::Contradiction) => true,
+            (Error::NotARule(rule), Error::NotARule(other)) => rule == other,
+            (Error::LimitExceeded(limit), Error::LimitExceeded(other)) => limit == other,
+            (Error::Io { path, cause }, Error::Io { path: other_path, cause: other_cause }) => {
+                path == other_path && cause.kind() == other_cause.kind()
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Parse { ref span, ref expected } => write!(f, "expected {} at {}", expected, span),
+            Error::Contradiction => write!(f, "facts are contradictory"),
+            Error::NotARule(ref rule) => write!(f, "{} is not a rule", rule),
+            Error::LimitExceeded(ref limit) => write!(f, "search stopped, {}", limit),
+            Error::Io { ref path, ref cause } => write!(f, "{}: {}", path, cause),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { ref cause, .. } => Some(&**cause),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Limit::Depth(depth) => write!(f, "depth limit of {} exceeded", depth),
+            Limit::Steps(steps) => write!(f, "step limit of {} exceeded", steps),
+            Limit::Time(time) => write!(f, "time limit of {} ms exceeded", time.as_millis()),
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "column {}", self.start + 1)
+    }
+}
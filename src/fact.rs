@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+
+/// Fact name interned to a compact id, cheap to copy, hash and compare.
+/// Names are case-insensitive and keep the spelling they were first interned with.
+/// Facts are ordered by id, lists meant to read alphabetically are sorted by `name`
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Fact(u32);
+
+struct Interner {
+    names: Vec<&'static str>,
+    /// Ids by uppercased name
+    ids: HashMap<String, u32>,
+}
+
+static INTERNER: Mutex<Option<Interner>> = Mutex::new(None);
+
+impl Fact {
+    /// Intern `name`, returning the same fact every time it's called with the same name in any case
+    pub fn new(name: &str) -> Fact {
+        let mut interner = INTERNER.lock().unwrap();
+        let interner = interner.get_or_insert_with(|| Interner { names: Vec::new(), ids: HashMap::new() });
+        let key = name.to_ascii_uppercase();
+
+        if let Some(&id) = interner.ids.get(&key) {
+            return Fact(id);
+        }
+
+        // Names live as long as the program, just like ids handed out for them
+        let name: &'static str = Box::leak(name.to_string().into_boxed_str());
+        let id = interner.names.len() as u32;
+
+        interner.names.push(name);
+        interner.ids.insert(key, id);
+
+        Fact(id)
+    }
+
+    /// Compact id, unique among all facts interned so far
+    pub fn id(self) -> usize {
+        self.0 as usize
+    }
+
+    /// Fact with id handed out by `new` before
+    pub(crate) fn from_id(id: usize) -> Fact {
+        Fact(id as u32)
+    }
+
+    pub fn name(self) -> &'static str {
+        INTERNER.lock().unwrap().as_ref().unwrap().names[self.id()]
+    }
+}
+
+impl From<&str> for Fact {
+    fn from(name: &str) -> Fact {
+        Fact::new(name)
+    }
+}
+
+impl fmt::Display for Fact {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl fmt::Debug for Fact {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Human readable metadata of a fact
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FactInfo {
+    pub description: Option<String>,
+    /// Question the consultant is asked about the fact
+    pub question: Option<String>,
+}
+
+impl FactInfo {
+    /// Question about `fact` to ask, made up from the description if there is none
+    pub fn question_about(info: Option<&FactInfo>, fact: Fact) -> String {
+        match info {
+            Some(FactInfo { question: Some(ref question), .. }) => question.clone(),
+            Some(FactInfo { description: Some(ref description), .. }) => format!("Is it true that {}?", description),
+            _ => format!("Is {} true?", fact),
+        }
+    }
+}
+
+/// Declaration tail in the input syntax, like `"patient has fever" ask "Does the patient have a fever?"`
+impl fmt::Display for FactInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ref description) = self.description {
+            write!(f, "{}", quote(description))?;
+        }
+
+        if let Some(ref question) = self.question {
+            if self.description.is_some() {
+                write!(f, " ")?;
+            }
+
+            write!(f, "ask {}", quote(question))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Put `text` in double quotes, escaping quotes and backslashes
+pub fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Reverse of `quote` for the text between the quotes
+pub fn unquote(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            res.extend(chars.next());
+        } else {
+            res.push(c);
+        }
+    }
+
+    res
+}
@@ -0,0 +1,206 @@
+use std::fmt;
+use std::iter::FromIterator;
+
+use super::Fact;
+
+const BITS: usize = 64;
+
+/// Set of facts stored as a bitset indexed by `Fact::id`, cheap to clone, merge and compare
+#[derive(Clone, Default)]
+pub struct FactSet {
+    words: Vec<u64>,
+}
+
+impl FactSet {
+    pub fn new() -> FactSet {
+        FactSet::default()
+    }
+
+    /// Add `fact`, returning `false` if it was already there
+    pub fn insert(&mut self, fact: Fact) -> bool {
+        let (word, bit) = (fact.id() / BITS, 1 << (fact.id() % BITS));
+
+        if word >= self.words.len() {
+            self.words.resize(word + 1, 0);
+        }
+
+        let had = self.words[word] & bit != 0;
+        self.words[word] |= bit;
+        !had
+    }
+
+    /// Remove `fact`, returning `false` if it wasn't there
+    pub fn remove(&mut self, fact: Fact) -> bool {
+        let (word, bit) = (fact.id() / BITS, 1 << (fact.id() % BITS));
+
+        match self.words.get_mut(word) {
+            Some(bits) if *bits & bit != 0 => {
+                *bits &= !bit;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn contains(&self, fact: Fact) -> bool {
+        let (word, bit) = (fact.id() / BITS, 1 << (fact.id() % BITS));
+
+        self.words.get(word).is_some_and(|bits| bits & bit != 0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.iter().map(|bits| bits.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|&bits| bits == 0)
+    }
+
+    /// Facts in the set, ordered by id
+    pub fn iter(&self) -> Iter<'_> {
+        Iter { words: &self.words, word: 0, bits: self.words.first().cloned().unwrap_or(0) }
+    }
+
+    pub fn union(&self, other: &FactSet) -> FactSet {
+        let (long, short) = if self.words.len() >= other.words.len() { (self, other) } else { (other, self) };
+        let mut res = long.clone();
+
+        for (bits, other) in res.words.iter_mut().zip(short.words.iter()) {
+            *bits |= other;
+        }
+
+        res
+    }
+
+    pub fn is_disjoint(&self, other: &FactSet) -> bool {
+        self.words.iter().zip(other.words.iter()).all(|(a, b)| a & b == 0)
+    }
+
+    /// Remove every fact that is in `other`
+    pub fn remove_all(&mut self, other: &FactSet) {
+        for (bits, other) in self.words.iter_mut().zip(other.words.iter()) {
+            *bits &= !other;
+        }
+    }
+}
+
+/// Sets are equal when they hold the same facts, however many empty words they carry
+impl PartialEq for FactSet {
+    fn eq(&self, other: &FactSet) -> bool {
+        let (long, short) = if self.words.len() >= other.words.len() { (self, other) } else { (other, self) };
+
+        long.words.iter().enumerate().all(|(idx, &bits)| bits == short.words.get(idx).cloned().unwrap_or(0))
+    }
+}
+
+impl Eq for FactSet {}
+
+impl fmt::Debug for FactSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl Extend<Fact> for FactSet {
+    fn extend<I: IntoIterator<Item = Fact>>(&mut self, iter: I) {
+        for fact in iter {
+            self.insert(fact);
+        }
+    }
+}
+
+impl FromIterator<Fact> for FactSet {
+    fn from_iter<I: IntoIterator<Item = Fact>>(iter: I) -> FactSet {
+        let mut res = FactSet::new();
+        res.extend(iter);
+        res
+    }
+}
+
+impl<'a> IntoIterator for &'a FactSet {
+    type Item = Fact;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+/// Iterator over facts of a `FactSet`
+pub struct Iter<'a> {
+    words: &'a [u64],
+    word: usize,
+    /// Bits of the current word that weren't yielded yet
+    bits: u64,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = Fact;
+
+    fn next(&mut self) -> Option<Fact> {
+        while self.bits == 0 {
+            self.word += 1;
+            self.bits = *self.words.get(self.word)?;
+        }
+
+        let bit = self.bits.trailing_zeros() as usize;
+        self.bits &= self.bits - 1;
+
+        Some(Fact::from_id(self.word * BITS + bit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(ids: &[usize]) -> FactSet {
+        ids.iter().map(|&id| Fact::from_id(id)).collect()
+    }
+
+    fn ids(set: &FactSet) -> Vec<usize> {
+        set.iter().map(Fact::id).collect()
+    }
+
+    #[test]
+    fn insert_and_remove_past_word_boundary() {
+        let mut set = FactSet::new();
+
+        assert!(set.insert(Fact::from_id(63)));
+        assert!(set.insert(Fact::from_id(64)));
+        assert!(!set.insert(Fact::from_id(64)));
+        assert!(set.contains(Fact::from_id(63)) && set.contains(Fact::from_id(64)));
+        assert!(!set.contains(Fact::from_id(65)) && !set.contains(Fact::from_id(200)));
+        assert_eq!(set.len(), 2);
+
+        assert!(set.remove(Fact::from_id(64)));
+        assert!(!set.remove(Fact::from_id(64)));
+        assert!(!set.remove(Fact::from_id(200)));
+        assert_eq!(ids(&set), vec![63]);
+    }
+
+    #[test]
+    fn union_of_sets_with_different_widths() {
+        let (short, long) = (set(&[1, 63]), set(&[0, 64, 130]));
+
+        assert_eq!(ids(&short.union(&long)), vec![0, 1, 63, 64, 130]);
+        assert_eq!(ids(&long.union(&short)), vec![0, 1, 63, 64, 130]);
+        assert!(short.is_disjoint(&long));
+    }
+
+    #[test]
+    fn iterates_in_id_order() {
+        let set = set(&[129, 3, 64, 0, 63, 200]);
+
+        assert_eq!(ids(&set), vec![0, 3, 63, 64, 129, 200]);
+    }
+
+    #[test]
+    fn empty_words_dont_matter() {
+        let mut wide = set(&[2, 150]);
+        wide.remove(Fact::from_id(150));
+
+        assert!(wide == set(&[2]));
+        assert!(!wide.is_empty() && set(&[150]) != set(&[]));
+    }
+}
@@ -1,21 +1,23 @@
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::fmt;
 
-#[derive(Clone, Debug, PartialEq)]
+use super::{ Fact, FactInfo, FactSet };
+
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct Facts {
-    pub yes: HashSet<char>,
-    pub no: HashSet<char>,
-    pub unknown: HashSet<char>,
+    pub yes: FactSet,
+    pub no: FactSet,
+    pub unknown: FactSet,
 }
 
 impl Facts {
-    pub fn new(yes: &[char], no: &[char], unknown: &[char]) -> Facts {
-        let yes: HashSet<char> = yes.iter().cloned().collect();
-        let no: HashSet<char> = no.iter().cloned().collect();
+    pub fn new(yes: &[Fact], no: &[Fact], unknown: &[Fact]) -> Facts {
+        let yes: FactSet = yes.iter().cloned().collect();
+        let no: FactSet = no.iter().cloned().collect();
         let unknown = unknown
             .iter()
-            .filter(|fact| !yes.contains(fact) && !no.contains(fact))
             .cloned()
+            .filter(|&fact| !yes.contains(fact) && !no.contains(fact))
             .collect();
 
         Facts { yes, no, unknown }
@@ -24,21 +26,16 @@ impl Facts {
     /// Merge `self` with `other`, returning `None` if facts are controversial
     /// and removing all known facts from unknown list
     pub fn merge(&self, other: &Facts) -> Option<Facts> {
-        let yes: HashSet<char> = self.yes.iter().chain(other.yes.iter()).cloned().collect();
-        let no: HashSet<char> = self.no.iter().chain(other.no.iter()).cloned().collect();
+        let yes = self.yes.union(&other.yes);
+        let no = self.no.union(&other.no);
 
-        for fact in yes.iter() {
-            if no.contains(fact) {
-                return None;
-            }
+        if !yes.is_disjoint(&no) {
+            return None;
         }
 
-        let unknown: HashSet<char> = self.unknown
-            .iter()
-            .chain(other.unknown.iter())
-            .filter(|fact| !yes.contains(fact) && !no.contains(fact))
-            .cloned()
-            .collect();
+        let mut unknown = self.unknown.union(&other.unknown);
+        unknown.remove_all(&yes);
+        unknown.remove_all(&no);
 
         Some(Facts { yes, no, unknown })
     }
@@ -61,50 +58,50 @@ impl Facts {
             return false;
         }
 
-        return true;
+        true
     }
 
-    pub fn is_yes(&self, c: char) -> bool {
-        self.yes.get(&c).is_some()
+    pub fn is_yes(&self, fact: Fact) -> bool {
+        self.yes.contains(fact)
     }
 
-    pub fn is_no(&self, c: char) -> bool {
-        self.no.get(&c).is_some()
+    pub fn is_no(&self, fact: Fact) -> bool {
+        self.no.contains(fact)
     }
 
-    pub fn is_unknown(&self, c: char) -> bool {
-        self.unknown.get(&c).is_some()
-        // && !self.yes.get(&c).is_some()
-        // && !self.no.get(&c).is_some()
+    pub fn is_unknown(&self, fact: Fact) -> bool {
+        self.unknown.contains(fact)
+        // && !self.yes.contains(fact)
+        // && !self.no.contains(fact)
     }
 
     /// Remove facts that are known in `other` from self
     pub fn remove_contained(&mut self, other: &Facts) {
-        for fact in other.yes.iter() {
-            self.yes.remove(fact);
-        }
-
-        for fact in other.no.iter() {
-            self.yes.remove(fact);
-        }
+        self.yes.remove_all(&other.yes);
+        self.yes.remove_all(&other.no);
     }
 }
 
-impl fmt::Display for Facts {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+impl Facts {
+    /// Display facts by their descriptions where there are any
+    pub fn describe<'a>(&'a self, info: &'a HashMap<Fact, FactInfo>) -> Described<'a> {
+        Described { facts: self, info }
+    }
+
+    fn fmt_names(&self, f: &mut fmt::Formatter, info: Option<&HashMap<Fact, FactInfo>>) -> fmt::Result {
         write!(f, "(")?;
 
         if !self.yes.is_empty() {
             write!(f, " true: ")?;
 
             let mut keys = self.yes.iter().collect::<Vec<_>>();
-            keys.sort();
+            keys.sort_by_key(|fact| fact.name());
 
             for (idx, key) in keys.iter().enumerate() {
                 if idx == 0 {
-                    write!(f, "{}", key);
+                    write!(f, "{}", Name(*key, info))?;
                 } else {
-                    write!(f, ", {}", key);
+                    write!(f, ", {}", Name(*key, info))?;
                 }
             }
 
@@ -115,13 +112,13 @@ impl fmt::Display for Facts {
             write!(f, " false: ")?;
 
             let mut keys = self.no.iter().collect::<Vec<_>>();
-            keys.sort();
+            keys.sort_by_key(|fact| fact.name());
 
             for (idx, key) in keys.iter().enumerate() {
                 if idx == 0 {
-                    write!(f, "{}", key);
+                    write!(f, "{}", Name(*key, info))?;
                 } else {
-                    write!(f, ", {}", key);
+                    write!(f, ", {}", Name(*key, info))?;
                 }
             }
 
@@ -132,13 +129,13 @@ impl fmt::Display for Facts {
             write!(f, " unknown: ")?;
 
             let mut keys = self.unknown.iter().collect::<Vec<_>>();
-            keys.sort();
+            keys.sort_by_key(|fact| fact.name());
 
             for (idx, key) in keys.iter().enumerate() {
                 if idx == 0 {
-                    write!(f, "{}", key);
+                    write!(f, "{}", Name(*key, info))?;
                 } else {
-                    write!(f, ", {}", key);
+                    write!(f, ", {}", Name(*key, info))?;
                 }
             }
 
@@ -148,3 +145,32 @@ impl fmt::Display for Facts {
         write!(f, ")")
     }
 }
+
+impl fmt::Display for Facts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_names(f, None)
+    }
+}
+
+/// Facts displayed along with fact descriptions
+pub struct Described<'a> {
+    facts: &'a Facts,
+    info: &'a HashMap<Fact, FactInfo>,
+}
+
+impl<'a> fmt::Display for Described<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.facts.fmt_names(f, Some(self.info))
+    }
+}
+
+struct Name<'a>(Fact, Option<&'a HashMap<Fact, FactInfo>>);
+
+impl<'a> fmt::Display for Name<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.1.and_then(|info| info.get(&self.0)).and_then(|info| info.description.as_ref()) {
+            Some(description) => write!(f, "{}", description),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
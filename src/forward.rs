@@ -0,0 +1,89 @@
+use std::collections::{ HashMap, VecDeque };
+
+use super::{ Error, Fact, Facts, Rule };
+use super::network::Network;
+use super::proof::Step;
+
+/// Fire every rule whose premise holds until nothing changes, starting from `given`.
+/// A rule with several possible outcomes only sets facts all of them agree on.
+/// Once a fact is set, only rules mentioning it are looked at again
+pub fn saturate(network: &Network, given: &Facts) -> Result<(Facts, HashMap<Fact, Step>), Error> {
+    let mut facts = Facts::new(&[], &[], &[]).merge(given).ok_or(Error::Contradiction)?;
+    let mut steps = facts.yes.iter().chain(facts.no.iter()).map(|fact| (fact, Step::Given)).collect();
+
+    let mut agenda = (0..network.rules().len()).collect::<VecDeque<_>>();
+    let mut queued = vec![true; network.rules().len()];
+
+    while let Some(idx) = agenda.pop_front() {
+        queued[idx] = false;
+
+        let forced = fire(network, idx, &mut facts, &mut steps)?;
+
+        for affected in network.mentioning(forced.yes.iter().chain(forced.no.iter())) {
+            if !queued[affected] {
+                queued[affected] = true;
+                agenda.push_back(affected);
+            }
+        }
+    }
+
+    Ok((facts, steps))
+}
+
+/// Set facts rule `idx` forces under `facts`, returning them
+pub fn fire(
+    network: &Network,
+    idx: usize,
+    facts: &mut Facts,
+    steps: &mut HashMap<Fact, Step>
+) -> Result<Facts, Error> {
+    let rule = network.rule(idx);
+
+    let outcomes = match rule {
+        Rule::IfThen(..) => match rule.try_match(facts)? {
+            Some(outcomes) => outcomes,
+            None => return Ok(Facts::default()),
+        },
+        constraint => constraint.possible_combinations_recursive(),
+    };
+
+    // Outcomes are small, so checking them against facts beats merging facts into each of them
+    let consistent = outcomes
+        .iter()
+        .filter(|outcome| {
+            !outcome.yes.iter().any(|fact| facts.is_no(fact)) && !outcome.no.iter().any(|fact| facts.is_yes(fact))
+        })
+        .collect::<Vec<_>>();
+    let first = consistent.first().ok_or(Error::Contradiction)?;
+
+    let forced = Facts::new(
+        &first.yes.iter().filter(|&fact| {
+            !facts.is_yes(fact) && consistent.iter().all(|other| other.is_yes(fact))
+        }).collect::<Vec<_>>(),
+        &first.no.iter().filter(|&fact| {
+            !facts.is_no(fact) && consistent.iter().all(|other| other.is_no(fact))
+        }).collect::<Vec<_>>(),
+        &[]);
+
+    if forced.is_empty(true, true, false) {
+        return Ok(forced);
+    }
+
+    let premises = network.step_premises(idx, facts);
+
+    for fact in forced.yes.iter().chain(forced.no.iter()) {
+        steps.insert(fact, Step::Rule {
+            rule: rule.clone(),
+            premises: premises.clone(),
+            outcome: forced.clone(),
+            forced: true,
+        });
+    }
+
+    facts.unknown.remove_all(&forced.yes);
+    facts.unknown.remove_all(&forced.no);
+    facts.yes.extend(forced.yes.iter());
+    facts.no.extend(forced.no.iter());
+
+    Ok(forced)
+}
@@ -0,0 +1,175 @@
+use std::collections::{ BTreeMap, HashSet };
+use std::fmt;
+use std::iter;
+
+use super::{ Fact, Facts, Rule };
+use super::engine::Answer;
+use super::fact::quote;
+use super::proof::{ Proof, Step };
+
+/// Dependency graph of rules, written in Graphviz DOT format by `Display`.
+/// Facts of the premise point to the rule and the rule points to facts of its conclusion,
+/// `<=>` rules get edges pointing both ways. Negated facts get dashed edges,
+/// facts joined by `|` get blue edges and facts joined by `^` orange ones
+pub struct Graph<'a> {
+    rules: &'a BTreeMap<usize, Rule>,
+    /// Rules and facts a query answer was established with, drawn filled
+    used_rules: HashSet<usize>,
+    used_facts: HashSet<Fact>,
+    /// Edges between a rule and the facts a step of the answer took from it or set by it, drawn bold
+    used_edges: HashSet<(usize, Fact)>,
+}
+
+/// Fact of one side of a rule, whether it's negated and the operator it's joined to the rest with
+#[derive(Clone, Copy, PartialEq)]
+struct Literal {
+    fact: Fact,
+    negated: bool,
+    operator: Option<&'static str>,
+}
+
+impl<'a> Graph<'a> {
+    pub fn new(rules: &'a BTreeMap<usize, Rule>) -> Graph<'a> {
+        Graph { rules, used_rules: HashSet::new(), used_facts: HashSet::new(), used_edges: HashSet::new() }
+    }
+
+    /// Highlight rules and facts that established the facts `find` asked about in `answer`
+    pub fn highlight(mut self, answer: &Answer, find: &Facts) -> Graph<'a> {
+        for fact in find.yes.iter().chain(find.no.iter()).chain(find.unknown.iter()) {
+            if let Some(proof) = answer.explain(fact) {
+                self.mark(&proof);
+            }
+        }
+
+        self
+    }
+
+    fn mark(&mut self, proof: &Proof) {
+        self.used_facts.insert(proof.fact);
+
+        if let Step::Rule { ref rule, ref premises, .. } = proof.step {
+            // Search works with the implications of `<=>` rules, not the rules themselves
+            let ids = self.rules
+                .iter()
+                .filter(|(_, other)| other == &rule || other.implications().is_ok_and(|all| all.contains(rule)))
+                .map(|(&id, _)| id)
+                .collect::<Vec<_>>();
+
+            for &id in ids.iter() {
+                let facts = premises.yes.iter().chain(premises.no.iter()).chain(iter::once(proof.fact));
+                self.used_edges.extend(facts.map(|fact| (id, fact)));
+            }
+
+            self.used_rules.extend(ids);
+        }
+
+        for premise in proof.premises.iter() {
+            self.mark(premise);
+        }
+    }
+
+    fn literals(rule: &Rule, negated: bool, operator: Option<&'static str>, res: &mut Vec<Literal>) {
+        use Rule::*;
+
+        match rule {
+            Fact(ref fact) => {
+                let literal = Literal { fact: *fact, negated, operator };
+                if !res.contains(&literal) {
+                    res.push(literal);
+                }
+            }
+            Not(ref l) => Graph::literals(l, !negated, operator, res),
+            And(ref l, ref r) | Or(ref l, ref r) | Xor(ref l, ref r) | IfThen(ref l, ref r) | IfAndOnlyIf(ref l, ref r) => {
+                let operator = match rule {
+                    And(..) => Some("+"),
+                    Or(..) => Some("|"),
+                    Xor(..) => Some("^"),
+                    _ => operator,
+                };
+
+                Graph::literals(l, negated, operator, res);
+                Graph::literals(r, negated, operator, res);
+            }
+        }
+    }
+
+    fn fmt_edge(&self, f: &mut fmt::Formatter, id: usize, literal: &Literal, incoming: bool, both: bool) -> fmt::Result {
+        let (fact, rule) = (quote(literal.fact.name()), quote(&format!("#{}", id)));
+        let mut attrs = Vec::new();
+
+        match literal.operator {
+            Some("|") => attrs.push("color=blue"),
+            Some("^") => attrs.push("color=darkorange"),
+            _ => {}
+        }
+
+        if literal.negated {
+            attrs.push("style=dashed");
+        }
+
+        if both {
+            attrs.push("dir=both");
+        }
+
+        if self.used_edges.contains(&(id, literal.fact)) {
+            attrs.push("penwidth=3");
+        }
+
+        let (from, to) = if incoming { (fact, rule) } else { (rule, fact) };
+
+        if attrs.is_empty() {
+            writeln!(f, "    {} -> {};", from, to)
+        } else {
+            writeln!(f, "    {} -> {} [{}];", from, to, attrs.join(", "))
+        }
+    }
+}
+
+impl<'a> fmt::Display for Graph<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "digraph rules {{")?;
+        writeln!(f, "    rankdir=LR;")?;
+
+        let mut facts = self.rules.values().flat_map(Rule::iter_facts).collect::<Vec<_>>();
+        facts.sort_by_key(|fact| fact.name());
+        facts.dedup();
+
+        for fact in facts {
+            if self.used_facts.contains(&fact) {
+                writeln!(f, "    {} [style=filled, fillcolor=yellow];", quote(fact.name()))?;
+            } else {
+                writeln!(f, "    {};", quote(fact.name()))?;
+            }
+        }
+
+        for (&id, rule) in self.rules.iter() {
+            let label = quote(&format!("#{} {}", id, rule));
+
+            if self.used_rules.contains(&id) {
+                writeln!(f, "    \"#{}\" [shape=box, label={}, style=filled, fillcolor=yellow];", id, label)?;
+            } else {
+                writeln!(f, "    \"#{}\" [shape=box, label={}];", id, label)?;
+            }
+
+            let (l, r, both) = match rule {
+                Rule::IfThen(ref l, ref r) => (&**l, &**r, false),
+                Rule::IfAndOnlyIf(ref l, ref r) => (&**l, &**r, true),
+                _ => continue,
+            };
+
+            let (mut premise, mut conclusion) = (Vec::new(), Vec::new());
+            Graph::literals(l, false, None, &mut premise);
+            Graph::literals(r, false, None, &mut conclusion);
+
+            for literal in premise.iter() {
+                self.fmt_edge(f, id, literal, true, both)?;
+            }
+
+            for literal in conclusion.iter() {
+                self.fmt_edge(f, id, literal, false, both)?;
+            }
+        }
+
+        writeln!(f, "}}")
+    }
+}
@@ -1,50 +1,172 @@
 #![feature(box_syntax, box_patterns)]
 
+pub mod fact;
+pub use fact::{ Fact, FactInfo };
+
+pub mod fact_set;
+pub use fact_set::FactSet;
+
 pub mod facts;
 pub use facts::Facts;
 
+pub mod assignment;
+pub use assignment::Assignment;
+
 pub mod rule;
 pub use rule::Rule;
 
+pub mod error;
+pub use error::{ Error, Limit, Span };
+
+pub mod trace;
+pub use trace::{ Event, Observer };
+
+pub mod consult;
+pub use consult::Consultant;
+
+pub mod proof;
+pub use proof::{ Proof, Step };
+
+pub mod verdict;
+pub use verdict::{ Reason, Truth, Verdict };
+
+pub mod sat;
+pub mod forward;
+
+pub mod network;
+pub use network::Network;
+
+pub mod oracle;
+pub use oracle::TruthTable;
+
+pub mod engine;
+pub use engine::{ Answer, Conflict, KnowledgeBase, Limits, Mode };
+
+pub mod graph;
+pub use graph::Graph;
+
+use std::time::Duration;
+
 #[derive(Debug, PartialEq)]
 pub enum Query {
     Rule(Rule),
-    Given(Facts),
+    /// Initial facts and constraint expressions that hold initially
+    Given(Facts, Vec<Rule>),
     Find(Facts),
     Dump,
+    /// Remove rules equivalent to this one
     Delete(Rule),
+    /// Remove rule by the id `dump` shows
+    DeleteId(usize),
+    Explain(Fact),
+    Limit(Limit),
+    Mode(Mode),
+    Check,
+    Consult(bool),
+    /// Description and question text of a fact
+    Describe(Fact, FactInfo),
+    Derive,
+    /// Print truth table of a rule or expression
+    Truth(Rule),
+    /// Rule dependency graph in DOT format, highlighting the last query's proof if asked,
+    /// written to a file or printed
+    Graph(bool, Option<String>),
+    /// Write rules, given facts and settings to a file
+    Save(String),
+    /// Replace the knowledge base with one read from a file
+    Load(String),
+    /// Empty or comment-only line
+    Empty,
 }
 
 peg::parser! {
     pub grammar parser() for str {
         pub rule query() -> Query
             = l:rul() { Query::Rule(l) }
-            / l:given() { Query::Given(l) }
+            / l:given() { Query::Given(l.0, l.1) }
             / l:find() { Query::Find(l) }
-            / "dump" { Query::Dump }
-            / "delete" whitespace()+ l:rul() { Query::Delete(l) }
+            / "dump" whitespace()? { Query::Dump }
+            / "delete" [' ' | '\t']+ "#" n:Number() whitespace()? { Query::DeleteId(n) }
+            / "delete" whitespace() l:rul() { Query::Delete(l) }
+            / "explain" whitespace() l:Ident() whitespace()? { Query::Explain(l) }
+            / "limit" whitespace() l:Limit() whitespace()? { Query::Limit(l) }
+            / "mode" whitespace() l:Mode() whitespace()? { Query::Mode(l) }
+            / "check" whitespace()? { Query::Check }
+            / "derive" whitespace()? { Query::Derive }
+            / "truth" whitespace() l:(rul() / Expr()) { Query::Truth(l) }
+            / "graph" p:(whitespace() "proof" !Path())? l:(whitespace() l:Path() { l })? whitespace()? {
+                Query::Graph(p.is_some(), l)
+            }
+            / "consult" whitespace() l:Switch() whitespace()? { Query::Consult(l) }
+            / "save" whitespace() l:Path() whitespace()? { Query::Save(l) }
+            / "load" whitespace() l:Path() whitespace()? { Query::Load(l) }
+            / "fact" whitespace() l:Ident() d:(whitespace() d:Text() { d })?
+              q:(whitespace() "ask" whitespace() q:Text() { q })? whitespace()? {
+                Query::Describe(l, FactInfo { description: d, question: q })
+            }
+            / whitespace()? { Query::Empty }
 
         pub rule rul() -> Rule
             = IfThen()
             / IfAndOnlyIf()
 
         rule whitespace()
-            = quiet!{[' ' | '\t']+}
+            = quiet!{([' ' | '\t']+ / comment())+}
+
+        rule comment()
+            = "#" (!['\n'] [_])*
 
         rule Atom() -> Rule
             = whitespace()? "(" whitespace()? l:Expr() whitespace()? ")" whitespace()? { l }
-            / whitespace()? l:Char() whitespace()? { l }
+            / whitespace()? l:Ident() whitespace()? { Rule::Fact(l) }
             / whitespace()? l:Not() whitespace()? { l }
 
-        rule Char() -> Rule
-            = c:$(['a'..='z' | 'A'..='Z']) { Rule::Char(c.chars().nth(0).unwrap().to_ascii_uppercase()) }
+        rule Ident() -> Fact
+            = quiet!{ c:$(['a'..='z' | 'A'..='Z' | '_'] ['a'..='z' | 'A'..='Z' | '0'..='9' | '_']*) { Fact::new(c) } }
+            / expected!("fact name")
+
+        rule Separator()
+            = whitespace()? "," whitespace()?
+            / whitespace()
+
+        /// Items of `=` and `?` starting with `!` or `(` need no separator before them, as in `=A!B`
+        rule ItemSeparator()
+            = Separator()
+            / &['!' | '(']
+
+        rule Number() -> usize
+            = n:$(['0'..='9']+) {? n.parse().or(Err("number")) }
+
+        /// Step and time limits of zero turn the limit off, time is in milliseconds
+        rule Limit() -> Limit
+            = "depth" whitespace() n:Number() { Limit::Depth(n) }
+            / "steps" whitespace() n:Number() { Limit::Steps(n) }
+            / "time" whitespace() n:Number() { Limit::Time(Duration::from_millis(n as u64)) }
+
+        rule Text() -> String
+            = "\"" l:$(("\\" [_] / !['"' | '\\'] [_])*) "\"" { fact::unquote(l) }
+
+        /// Quoted to have spaces in it
+        rule Path() -> String
+            = Text()
+            / l:$((![' ' | '\t' | '\n' | '#'] [_])+) { l.to_string() }
+
+        rule Switch() -> bool
+            = "on" { true }
+            / "off" { false }
+
+        rule Mode() -> Mode
+            = "search" { Mode::Search }
+            / "exact" { Mode::Exact }
 
         rule Not() -> Rule
             = "!" l:Atom() { Rule::Not(box l) }
 
         rule Expr() -> Rule = precedence!{
             l:(@) "^" r:@ { Rule::Xor(box l, box r) }
+            --
             l:(@) "|" r:@ { Rule::Or(box l, box r) }
+            --
             l:(@) "+" r:@ { Rule::And(box l, box r) }
             --
             l:Atom() { l }
@@ -56,10 +178,52 @@ peg::parser! {
         rule IfAndOnlyIf() -> Rule
             = l:Expr() "<=>" r:Expr() { Rule::IfAndOnlyIf(box l, box r) }
 
-        pub rule given() -> Facts
-            = "=" c:$(['a'..='z' | 'A'..='Z']*) { Facts::new(&c.chars().collect::<Vec<_>>(), &[], &[]) }
+        /// Plain facts go to true or false facts, parenthesized items are constraints
+        pub rule given() -> (Facts, Vec<Rule>)
+            = "=" whitespace()? l:(GivenItem() ** ItemSeparator()) whitespace()? {
+                let mut facts = Facts::default();
+                let mut constraints = Vec::new();
+
+                for (item, parenthesized) in l {
+                    match item {
+                        _ if parenthesized => constraints.push(item),
+                        Rule::Fact(fact) => { facts.yes.insert(fact); }
+                        Rule::Not(box Rule::Fact(fact)) => { facts.no.insert(fact); }
+                        rule => constraints.push(rule),
+                    }
+                }
+
+                (facts, constraints)
+            }
+
+        rule GivenItem() -> (Rule, bool)
+            = l:Ident() { (Rule::Fact(l), false) }
+            / "!" l:Ident() { (Rule::Fact(l).negated(), false) }
+            / "(" whitespace()? l:(rul() / Expr()) whitespace()? ")" { (l, true) }
 
         pub rule find() -> Facts
-            = "?" c:$(['a'..='z' | 'A'..='Z']+) { Facts::new(&[], &[], &c.chars().collect::<Vec<_>>()) }
+            = "?" whitespace()? l:(FindItem() ++ ItemSeparator()) whitespace()? { Facts::new(&[], &[], &l) }
+
+        /// `!X` asks about `X` just like `X` does
+        rule FindItem() -> Fact
+            = "!"? l:Ident() { l }
     }
 }
+
+/// Parse a line of input, on error the span covers the token the parser stumbled on
+pub fn parse(line: &str) -> Result<Query, Error> {
+    parser::query(line).map_err(|e| {
+        let start = e.location.offset;
+        let rest = &line[start..];
+
+        // Rest of the word at the error, or a single character if it's a space
+        let len = match rest.find(char::is_whitespace) {
+            Some(0) => rest.chars().next().map_or(0, char::len_utf8),
+            Some(len) => len,
+            None => rest.len(),
+        };
+        let end = start + len;
+
+        Error::Parse { span: Span { start, end }, expected: e.expected.to_string() }
+    })
+}
@@ -1,167 +1,441 @@
 use crossterm::style::Colorize;
-use expert_system::{parser, Facts, Query, Rule};
+use expert_system::{
+    parse, Answer, Assignment, Consultant, Error, Event, Fact, FactInfo, Facts, FactSet, KnowledgeBase, Mode, Observer,
+    Query, Rule, Step, TruthTable,
+};
 use rustyline::error::ReadlineError;
-use std::collections::{ HashSet, HashMap };
-
-fn run(
-    rules: &HashSet<Rule>,
-    used_rules: HashMap<Rule, bool>,
-    mut facts: Facts,
-    level: usize
-) -> Facts {
-    macro_rules! levelprintln {
-        ($fmt:literal) => {
-            println!(concat!("{}", $fmt), "  ".repeat(level))
-        };
-        ($fmt:literal, $( $args:expr ),*) => {
-            println!(concat!("{}", $fmt), "  ".repeat(level), $( $args ),*)
-        };
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::{ env, fmt, fs, mem, process };
+use std::sync::Arc;
+
+/// Print solver reasoning as an indented colored tree, naming facts by their descriptions
+struct Printer<'a>(&'a HashMap<Fact, FactInfo>);
+
+impl<'a> Observer for Printer<'a> {
+    fn event(&mut self, event: &Event) {
+        let indent = "  ".repeat(event.depth());
+
+        match *event {
+            Event::Entered { facts, .. } => {
+                println!("{}{}", indent, facts.describe(self.0).to_string().green())
+            }
+            Event::RuleTried { rule, .. } => println!("{}Using {}", indent, rule.to_string().blue()),
+            Event::Outcomes { outcomes, .. } => {
+                println!("{}{} possible outcome{}",
+                         indent,
+                         outcomes.len(),
+                         if outcomes.len() > 1 { "s" } else { "" })
+            }
+            Event::OutcomeTried { outcome, .. } => println!("{}Trying with {}", indent, outcome),
+            Event::NoProgress { .. } => {
+                println!("{}{}", indent, "Does not give needed facts".to_string().yellow())
+            }
+            Event::Conflict { .. } => println!("{}{}", indent, "Conflict".to_string().red()),
+            Event::NoMatch { .. } => println!("{}{}", indent, "No match".to_string().yellow()),
+            Event::Cycle { .. } => println!("{}{}", indent, "Cycle, not expanding".to_string().yellow()),
+            Event::Asked { fact, answer, .. } => {
+                let answer = match answer {
+                    Some(true) => "true",
+                    Some(false) => "false",
+                    None => "unknown",
+                };
+                let name = match self.0.get(&fact).and_then(|info| info.description.as_ref()) {
+                    Some(description) => description.clone(),
+                    None => fact.to_string(),
+                };
+                println!("{}Asked about {}: {}", indent, name, answer.to_string().cyan())
+            }
+            Event::Resolved { .. } => println!("{}Unknown list is empty, returning", indent),
+        }
     }
+}
 
-    if level > 20 {
-        panic!("recursion level too big");
+/// Ask the user about facts through the line editor
+struct Prompter<'a>(&'a mut rustyline::Editor<()>);
+
+impl<'a> Consultant for Prompter<'a> {
+    fn ask(&mut self, fact: Fact, info: Option<&FactInfo>) -> Option<bool> {
+        let prompt = format!("{} [y/n/unknown] ", FactInfo::question_about(info, fact));
+
+        loop {
+            match self.0.readline(&prompt) {
+                Ok(line) => match line.trim() {
+                    "y" | "yes" => return Some(true),
+                    "n" | "no" => return Some(false),
+                    "" | "u" | "unknown" => return None,
+                    _ => continue,
+                },
+                Err(_) => return None,
+            }
+        }
     }
+}
 
-    levelprintln!("{}", facts.to_string().green());
+/// Why a query failed, along with the file lines it was loaded from
+enum SessionError {
+    Query(Error),
+    /// Line `line` of the file at `path` failed
+    Line { path: String, line: usize, cause: Box<SessionError> },
+    /// File at the path loads itself, directly or through other files
+    Cycle(String),
+}
 
-    if facts.is_empty(false, false, true) {
-        levelprintln!("Unknown list is empty, returning");
-        return facts;
+impl From<Error> for SessionError {
+    fn from(e: Error) -> SessionError {
+        SessionError::Query(e)
     }
+}
 
-    for rule in rules.iter() {
-        if let Rule::IfThen(ref l, ref r) = rule {
-            let (l_facts, r_facts) =
-                (l.iter_facts().collect::<Vec<_>>(), r.iter_facts().collect::<Vec<_>>());
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SessionError::Query(ref e) => write!(f, "{}", e),
+            SessionError::Line { ref path, line, ref cause } => write!(f, "{}:{}: {}", path, line, cause),
+            SessionError::Cycle(ref path) => write!(f, "{} is already being loaded", path),
+        }
+    }
+}
 
-            if used_rules.get(rule) == Some(&true) {
-                continue;
-            }
+struct Session {
+    kb: KnowledgeBase,
+    editor: rustyline::Editor<()>,
+    /// Ask the user about facts no rule concludes
+    consult: bool,
+    /// Facts the last query answered by search asked about and its answer
+    last: Option<(Facts, Answer)>,
+    /// Files being run, outermost first
+    loading: Vec<PathBuf>,
+}
 
-            if r_facts.iter().any(|&fact| facts.is_unknown(fact)) {
-                levelprintln!("Using {}", rule.to_string().blue());
+impl Session {
+    fn new() -> Session {
+        Session { kb: KnowledgeBase::new(), editor: rustyline::Editor::<()>::new(), consult: false, last: None, loading: Vec::new() }
+    }
 
-                if let Some(outcomes) = rule.try_match(&facts) {
-                    levelprintln!("{} possible outcome{}",
-                                  outcomes.len(),
-                                  if outcomes.len() > 1 { "s" } else { "" });
+    /// Rule dependency graph in DOT format, highlighting how the last query was answered if `proof`
+    fn graph(&self, proof: bool) -> String {
+        match self.last {
+            Some((ref find, ref answer)) if proof => self.kb.graph().highlight(answer, find).to_string(),
+            _ => {
+                if proof {
+                    eprintln!("No query answered by search to highlight");
+                }
 
-                    for outcome in outcomes.iter() {
-                        levelprintln!("Trying with {}", outcome);
+                self.kb.graph().to_string()
+            }
+        }
+    }
 
-                        if let Some(merged_facts) = facts.merge(outcome) {
-                            // levelprintln!("Merged {}", merged_facts.to_string().cyan());
-                            if merged_facts.unknown.len() == facts.unknown.len() {
-                                levelprintln!("{}", "Does not give needed facts".to_string().yellow());
-                                continue;
-                            }
+    /// Apply `query`, printing every reasoning step if `verbose`
+    fn execute(&mut self, query: Query, verbose: bool) -> Result<(), SessionError> {
+        let kb = &mut self.kb;
 
-                            let mut used_rules = used_rules.clone();
-                            used_rules.insert(rule.clone(), true);
+        // The last search answer no longer matches what `graph` would draw
+        let stale = match query {
+            Query::Rule(_) | Query::Given(..) | Query::Delete(_) | Query::DeleteId(_) | Query::Load(_) => true,
+            Query::Find(_) => kb.mode() == Mode::Exact,
+            _ => false,
+        };
+        if stale {
+            self.last = None;
+        }
+
+        match query {
+            Query::Rule(rule) => {
+                if verbose {
+                    println!("Rule: {}", rule);
+                }
+                // println!("Mentioned facts: {:?}", rule.iter_facts().collect::<Vec<_>>());
+                // let possible_inputs = rule.possible_inputs();
+                // for input in possible_inputs.iter() {
+                //     println!("Possible input: {}", input);
+                // }
+                // let possible_outputs = rule.possible_outputs();
+                // for output in possible_outputs.iter() {
+                //     println!("Possible output: {}", output);
+                // }
+                kb.add_rule(rule)?;
+            }
+            Query::Given(list, constraints) => {
+                if verbose {
+                    print!("Have: {}", list);
+                    for constraint in constraints.iter() {
+                        print!(" ({})", constraint);
+                    }
+                    println!();
+                }
+                let mentioned = list.yes.union(&list.no);
+                kb.set_given(list)?;
+                kb.set_constraints(constraints);
+                warn_unmentioned(kb, &mentioned);
+            }
+            Query::Find(find) => {
+                if verbose {
+                    println!("Find: {}", find);
+                }
+                warn_unmentioned(kb, &find.unknown);
 
-                            let num_unknown = facts.unknown.len();
-                            let new_facts = run(rules, used_rules, merged_facts, level + 1);
-                            if new_facts.unknown.is_empty() {
-                                return new_facts;
-                            } else if new_facts.unknown.len() < num_unknown {
-                                facts = new_facts;
-                            }
+                let (mut printer, mut silent) = (Printer(kb.info()), ());
+                let observer: &mut dyn Observer = if verbose { &mut printer } else { &mut silent };
+
+                let verdicts = match kb.mode() {
+                    Mode::Search => {
+                        let answer = if self.consult {
+                            kb.consult(&find, observer, &mut Prompter(&mut self.editor))?
                         } else {
-                            levelprintln!("{}", "Conflict".to_string().red());
-                        }
+                            kb.solve(&find, observer)?
+                        };
+
+                        let verdicts = answer.verdicts(&find);
+                        self.last = Some((find, answer));
+                        verdicts
                     }
-                } else {
-                    if used_rules.get(rule) == Some(&false) {
-                        levelprintln!("{}", "No match".to_string().yellow());
-                        continue;
+                    Mode::Exact => kb.entailed(&find)?,
+                };
+
+                let verdicts = verdicts.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+                println!("Result: {}", verdicts.join(", "));
+            }
+            Query::Dump => {
+                println!("*** Rules:");
+                for (id, rule) in kb.rules().iter() {
+                    println!("***   #{} {}", id, rule);
+                }
+
+                let mut described = kb.info().iter().collect::<Vec<_>>();
+                described.sort_by_key(|&(fact, _)| fact.name());
+
+                if !described.is_empty() {
+                    println!("*** Descriptions:");
+                    for (fact, info) in described {
+                        println!("***   fact {} {}", fact, info);
                     }
+                }
 
-                    let mut used_rules = used_rules.clone();
-                    used_rules.insert(rule.clone(), false);
+                print!("*** Facts: {}", kb.given().describe(kb.info()));
+                for constraint in kb.constraints().iter() {
+                    print!(" ({})", constraint);
+                }
+                println!();
+            }
+            Query::Delete(rule) => {
+                let removed = kb.remove_equivalent(&rule);
+                if removed.is_empty() {
+                    eprintln!("Rule not found");
+                }
 
-                    let mut facts = facts.clone();
-                    for l_fact in l_facts.iter() {
-                        if !facts.is_yes(*l_fact) && !facts.is_no(*l_fact) {
-                            facts.unknown.insert(*l_fact);
-                        }
+                if verbose {
+                    for (id, rule) in removed {
+                        println!("Deleted: #{} {}", id, rule);
+                    }
+                }
+            }
+            Query::DeleteId(id) => match kb.remove_rule_by_id(id) {
+                Some(rule) => {
+                    if verbose {
+                        println!("Deleted: #{} {}", id, rule);
                     }
+                }
+                None => eprintln!("Rule #{} not found", id),
+            },
+            Query::Explain(fact) => match kb.explain(fact)? {
+                Some(proof) => print!("{}", proof),
+                None => println!("{} can't be established", fact),
+            },
+            Query::Limit(limit) => {
+                let mut limits = *kb.limits();
+                limits.set(limit);
+                kb.set_limits(limits);
+            }
+            Query::Mode(mode) => kb.set_mode(mode),
+            Query::Consult(consult) => self.consult = consult,
+            Query::Derive => {
+                let answer = kb.derive()?;
+                println!("Derived: {}", answer.facts.describe(kb.info()));
 
-                    let num_unknown = facts.unknown.len();
-                    let new_facts = run(rules, used_rules, facts, level + 1);
-                    if new_facts.unknown.is_empty() {
-                        return new_facts;
-                    } else if new_facts.unknown.len() < num_unknown {
-                        facts = new_facts;
+                let mut derived = answer.steps
+                    .iter()
+                    .filter(|(_, step)| step != &&Step::Given)
+                    .map(|(&fact, _)| fact)
+                    .collect::<Vec<_>>();
+                derived.sort_by_key(|fact| fact.name());
+
+                for fact in derived {
+                    println!("  {}", answer.verdict(fact));
+                }
+            }
+            Query::Describe(fact, info) => {
+                if verbose {
+                    println!("Fact: {} {}", fact, info);
+                }
+                kb.describe(fact, info);
+            }
+            Query::Check => match kb.check() {
+                Some(conflict) => {
+                    println!("*** Contradiction:");
+                    for line in conflict.to_string().lines() {
+                        println!("***   {}", line);
                     }
+
+                    return Err(Error::Contradiction.into());
+                }
+                None => println!("Consistent"),
+            },
+            Query::Truth(rule) => print_truth_table(&rule),
+            Query::Graph(proof, None) => print!("{}", self.graph(proof)),
+            Query::Graph(proof, Some(path)) => {
+                fs::write(&path, self.graph(proof)).map_err(|cause| Error::Io { path, cause: Arc::new(cause) })?
+            }
+            Query::Save(path) => kb.save(&path)?,
+            Query::Load(path) => {
+                let previous = mem::replace(kb, KnowledgeBase::new());
+
+                let result = self.run_file(&path);
+                if result.is_err() {
+                    self.kb = previous;
                 }
+
+                return result;
             }
+            Query::Empty => {}
+        }
+
+        Ok(())
+    }
+
+    /// Run every line of the file at `path`, printing only results and stopping at the first error
+    fn run_file(&mut self, path: &str) -> Result<(), SessionError> {
+        let io = |cause| Error::Io { path: path.to_string(), cause: Arc::new(cause) };
+        let text = fs::read_to_string(path).map_err(io)?;
+        let canonical = fs::canonicalize(path).map_err(io)?;
+
+        if self.loading.contains(&canonical) {
+            return Err(SessionError::Cycle(path.to_string()));
+        }
+
+        self.loading.push(canonical);
+
+        let result = text.lines().enumerate().try_for_each(|(idx, line)| {
+            let located = |cause| SessionError::Line { path: path.to_string(), line: idx + 1, cause: Box::new(cause) };
+
+            let query = parse(line.trim_end()).map_err(|e| located(e.into()))?;
+            self.execute(query, false).map_err(located)
+        });
+
+        self.loading.pop();
+        result
+    }
+}
+
+/// Warn about facts no rule or constraint mentions, like `AJK` from a file written as `=AJK` for three facts
+fn warn_unmentioned(kb: &KnowledgeBase, facts: &FactSet) {
+    let mut unmentioned = facts.clone();
+    for rule in kb.rules().values().chain(kb.constraints().iter()) {
+        for fact in rule.iter_facts() {
+            unmentioned.remove(fact);
         }
     }
 
-    return facts;
+    let mut unmentioned = unmentioned.iter().collect::<Vec<_>>();
+    unmentioned.sort_by_key(|fact| fact.name());
+
+    for fact in unmentioned {
+        eprintln!("Warning: no rule mentions {}", fact);
+    }
+}
+
+/// Print every assignment of facts mentioned by `rule`, with values of its sides and of the rule itself
+fn print_truth_table(rule: &Rule) {
+    let mut facts = rule.iter_facts().collect::<Vec<_>>();
+    facts.sort_by_key(|fact| fact.name());
+    facts.dedup();
+
+    if facts.len() > TruthTable::MAX_FACTS {
+        eprintln!("Too many facts for a truth table, at most {} are allowed", TruthTable::MAX_FACTS);
+        return;
+    }
+
+    let columns = match rule {
+        Rule::IfThen(ref l, ref r) | Rule::IfAndOnlyIf(ref l, ref r) => vec![&**l, &**r, rule],
+        _ => vec![rule],
+    };
+
+    let fact_names = facts.iter().map(|fact| fact.to_string()).collect::<Vec<_>>();
+    let column_names = columns.iter().map(|column| column.to_string()).collect::<Vec<_>>();
+    println!("{} | {}", fact_names.join(" "), column_names.join(" | "));
+
+    for bits in 0..1u64 << facts.len() {
+        let mut assignment = Assignment::new();
+        let mut values = Vec::with_capacity(facts.len());
+
+        // First fact changes slowest, so that rows are in counting order
+        for (idx, (&fact, name)) in facts.iter().zip(fact_names.iter()).enumerate() {
+            let value = bits & 1 << (facts.len() - 1 - idx) != 0;
+            assignment.set(fact, value);
+            values.push(format!("{:^1$}", value as u8, name.len()));
+        }
+
+        let results = columns
+            .iter()
+            .zip(column_names.iter())
+            .map(|(column, name)| format!("{:^1$}", column.evaluate(&assignment) as u8, name.len()))
+            .collect::<Vec<_>>();
+
+        println!("{} | {}", values.join(" "), results.join(" | ").trim_end());
+    }
+}
+
+/// Run every line of `paths` in a single session, stopping at the first error
+fn batch(paths: &[String]) -> bool {
+    let mut session = Session::new();
+
+    for path in paths.iter() {
+        if let Err(e) = session.run_file(path) {
+            eprintln!("{}", e);
+            return false;
+        }
+    }
+
+    true
 }
 
 fn main() {
-    let mut rl = rustyline::Editor::<()>::new();
-    let mut rules = HashSet::new();
-    let mut facts = Facts::new(&[], &[], &[]);
+    let paths = env::args().skip(1).collect::<Vec<_>>();
+
+    if !paths.is_empty() {
+        process::exit(if batch(&paths) { 0 } else { 1 });
+    }
+
+    let mut session = Session::new();
 
     loop {
-        match rl.readline("> ") {
+        match session.editor.readline("> ") {
             Ok(line) => {
                 let line = line.trim_end();
 
-                match parser::query(&line) {
-                    Ok(query) => match query {
-                        Query::Rule(rule) => {
-                            println!("Rule: {}", rule);
-                            // println!("Mentioned facts: {:?}", rule.iter_facts().collect::<Vec<_>>());
-                            // let possible_inputs = rule.possible_inputs();
-                            // for input in possible_inputs.iter() {
-                            //     println!("Possible input: {}", input);
-                            // }
-                            // let possible_outputs = rule.possible_outputs();
-                            // for output in possible_outputs.iter() {
-                            //     println!("Possible output: {}", output);
-                            // }
-                            rules.insert(rule);
-                        }
-                        Query::Given(list) => {
-                            println!("Have: {}", &list);
-                            facts = list;
-                        }
-                        Query::Find(find) => {
-                            println!("Find: {}", find);
-
-                            facts = facts.merge(&find).unwrap();
-                            let result = run(&rules, HashMap::new(), facts.clone(), 0);
-                            println!("Result: {}", result);
+                match parse(line) {
+                    Ok(query) => {
+                        if let Err(e) = session.execute(query, true) {
+                            eprintln!("{}", e);
                         }
-                        Query::Dump => {
-                            println!("*** Rules:");
-                            for rule in rules.iter() {
-                                println!("***   {}", rule);
-                            }
-
-                            println!("*** Facts: {}", facts);
-                        }
-                        Query::Delete(rule) => {
-                            if !rules.remove(&rule) {
-                                eprintln!("Rule not found");
-                            }
-                        }
-                    },
+                    }
                     Err(e) => {
-                        eprintln!("  {}^", " ".repeat(e.location.offset));
+                        if let Error::Parse { span, .. } = e {
+                            eprintln!("  {}{}", " ".repeat(span.start), "^".repeat((span.end - span.start).max(1)));
+                        }
                         eprintln!("{}", e);
                     }
                 }
 
-                rl.add_history_entry(line);
+                session.editor.add_history_entry(line);
             }
             Err(ReadlineError::Interrupted) => {}
             Err(ReadlineError::Eof) => break,
-            Err(err) => panic!("{}", err),
+            Err(err) => {
+                eprintln!("{}", err);
+                break;
+            }
         }
     }
 }
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use super::{ Fact, Facts, Rule };
+
+/// Rules indexed by the facts they mention, so that a changed fact only
+/// brings up the rules it can affect instead of scanning all of them
+#[derive(Clone, Debug, Default)]
+pub struct Network {
+    rules: Vec<Rule>,
+    premises: Vec<Vec<Fact>>,
+    conclusions: Vec<Vec<Fact>>,
+    /// Rules mentioning a fact anywhere
+    mentions: HashMap<Fact, Vec<usize>>,
+    /// Rules mentioning a fact in the conclusion
+    concludes: HashMap<Fact, Vec<usize>>,
+}
+
+impl Network {
+    /// Index `IfThen` rules and constraints, a constraint is all conclusion and no premise
+    pub fn new(rules: Vec<Rule>) -> Network {
+        let mut network = Network::default();
+
+        for (idx, rule) in rules.iter().enumerate() {
+            let (premise, conclusion) = match rule {
+                Rule::IfThen(ref l, ref r) => (l.iter_facts().collect(), r.iter_facts().collect()),
+                constraint => (Vec::new(), constraint.iter_facts().collect::<Vec<_>>()),
+            };
+
+            for &fact in premise.iter().chain(conclusion.iter()) {
+                let entry = network.mentions.entry(fact).or_insert_with(Vec::new);
+                if entry.last() != Some(&idx) {
+                    entry.push(idx);
+                }
+            }
+
+            for &fact in conclusion.iter() {
+                let entry = network.concludes.entry(fact).or_insert_with(Vec::new);
+                if entry.last() != Some(&idx) {
+                    entry.push(idx);
+                }
+            }
+
+            network.premises.push(premise);
+            network.conclusions.push(conclusion);
+        }
+
+        network.rules = rules;
+        network
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    pub fn rule(&self, idx: usize) -> &Rule {
+        &self.rules[idx]
+    }
+
+    /// Facts in the premise of rule `idx`
+    pub fn premise(&self, idx: usize) -> &[Fact] {
+        &self.premises[idx]
+    }
+
+    /// Facts in the conclusion of rule `idx`
+    pub fn conclusion(&self, idx: usize) -> &[Fact] {
+        &self.conclusions[idx]
+    }
+
+    /// Facts of rule `idx` known in `facts`, which a step firing the rule depends on.
+    /// Known facts of the conclusion rule out other outcomes, so they count along with the premise
+    pub fn step_premises(&self, idx: usize, facts: &Facts) -> Facts {
+        let known = self.premise(idx).iter().chain(self.conclusion(idx).iter()).cloned();
+
+        Facts::new(
+            &known.clone().filter(|&fact| facts.is_yes(fact)).collect::<Vec<_>>(),
+            &known.filter(|&fact| facts.is_no(fact)).collect::<Vec<_>>(),
+            &[])
+    }
+
+    /// Whether some rule concludes `fact`
+    pub fn is_derivable(&self, fact: Fact) -> bool {
+        self.concludes.contains_key(&fact)
+    }
+
+    /// Rules that conclude any of `facts`, in the order they were given
+    pub fn concluding<I>(&self, facts: I) -> Vec<usize>
+    where
+        I: IntoIterator<Item = Fact>,
+    {
+        Network::collect(&self.concludes, facts)
+    }
+
+    /// Rules that mention any of `facts`, in the order they were given
+    pub fn mentioning<I>(&self, facts: I) -> Vec<usize>
+    where
+        I: IntoIterator<Item = Fact>,
+    {
+        Network::collect(&self.mentions, facts)
+    }
+
+    fn collect<I>(index: &HashMap<Fact, Vec<usize>>, facts: I) -> Vec<usize>
+    where
+        I: IntoIterator<Item = Fact>,
+    {
+        let mut res = facts
+            .into_iter()
+            .filter_map(|fact| index.get(&fact))
+            .flat_map(|rules| rules.iter().cloned())
+            .collect::<Vec<_>>();
+
+        res.sort();
+        res.dedup();
+        res
+    }
+}
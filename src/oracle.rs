@@ -0,0 +1,101 @@
+use super::{ Assignment, Fact, Facts, Rule };
+use super::verdict::Truth;
+
+/// Every assignment of facts that satisfies rules and given facts, found by trying all of them.
+/// It's exponential in the number of facts and meant as a reference to check the engine against
+#[derive(Clone, Debug, PartialEq)]
+pub struct TruthTable {
+    facts: Vec<Fact>,
+    /// Satisfying assignments, bit `i` is the value of `facts[i]`
+    models: Vec<u64>,
+}
+
+impl TruthTable {
+    /// Most facts a table is built for
+    pub const MAX_FACTS: usize = 20;
+
+    /// Table of assignments where all `rules` hold and `given` facts have their values,
+    /// or `None` if there are more than `MAX_FACTS` facts. Constraints go along with rules
+    pub fn new<'a, I>(rules: I, given: &Facts) -> Option<TruthTable>
+    where
+        I: IntoIterator<Item = &'a Rule>,
+    {
+        let rules = rules.into_iter().collect::<Vec<_>>();
+
+        let mut facts = rules
+            .iter()
+            .flat_map(|rule| rule.iter_facts())
+            .chain(given.yes.iter())
+            .chain(given.no.iter())
+            .collect::<Vec<_>>();
+        facts.sort_by_key(|fact| fact.name());
+        facts.dedup();
+
+        if facts.len() > TruthTable::MAX_FACTS {
+            return None;
+        }
+
+        let models = (0..1u64 << facts.len())
+            .filter(|&bits| {
+                // `MAX_FACTS` facts fit in the bits
+                Assignment::from_bits(&facts, bits).is_some_and(|assignment| {
+                    facts.iter().all(|&fact| !(given.is_yes(fact) && !assignment.get(fact)))
+                        && facts.iter().all(|&fact| !(given.is_no(fact) && assignment.get(fact)))
+                        && rules.iter().all(|rule| rule.evaluate(&assignment))
+                })
+            })
+            .collect();
+
+        Some(TruthTable { facts, models })
+    }
+
+    /// Facts mentioned by rules or given, sorted by name
+    pub fn facts(&self) -> &[Fact] {
+        &self.facts
+    }
+
+    /// Satisfying assignments, each fact of the table is either true or false in them
+    pub fn models<'a>(&'a self) -> impl Iterator<Item = Facts> + 'a {
+        self.models.iter().map(move |&bits| TruthTable::assignment(&self.facts, bits))
+    }
+
+    /// Whether rules and given facts can all hold together
+    pub fn is_consistent(&self) -> bool {
+        !self.models.is_empty()
+    }
+
+    /// True or false if `fact` has that value in every model, or `None` if there are no models.
+    /// Facts the table doesn't mention can have any value
+    pub fn truth(&self, fact: Fact) -> Option<Truth> {
+        if !self.is_consistent() {
+            return None;
+        }
+
+        let idx = match self.facts.iter().position(|&other| other == fact) {
+            Some(idx) => idx,
+            None => return Some(Truth::Undetermined),
+        };
+
+        if self.models.iter().all(|bits| bits & 1 << idx != 0) {
+            Some(Truth::True)
+        } else if self.models.iter().all(|bits| bits & 1 << idx == 0) {
+            Some(Truth::False)
+        } else {
+            Some(Truth::Undetermined)
+        }
+    }
+
+    fn assignment(facts: &[Fact], bits: u64) -> Facts {
+        let mut assignment = Facts::default();
+
+        for (idx, &fact) in facts.iter().enumerate() {
+            if bits & 1 << idx != 0 {
+                assignment.yes.insert(fact);
+            } else {
+                assignment.no.insert(fact);
+            }
+        }
+
+        assignment
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use super::{ Fact, Facts, Rule };
+
+/// How a fact got its value
+#[derive(Clone, Debug, PartialEq)]
+pub enum Step {
+    /// Fact was given initially
+    Given,
+    /// Consultant told the value of the fact
+    Answered,
+    /// Fact was set by chosen `outcome` of `rule`. `premises` are known facts of the rule,
+    /// which made its premise hold and ruled out other outcomes.
+    /// `forced` is false if other possible outcomes would give the fact another value
+    Rule { rule: Rule, premises: Facts, outcome: Facts, forced: bool },
+}
+
+/// Derivation tree of a single fact
+#[derive(Clone, Debug, PartialEq)]
+pub struct Proof {
+    pub fact: Fact,
+    pub value: bool,
+    pub step: Step,
+    pub premises: Vec<Proof>,
+}
+
+impl Proof {
+    /// Build derivation of `fact` from `steps` recorded while solving into `facts`,
+    /// returning `None` if the fact wasn't established
+    pub fn build(fact: Fact, facts: &Facts, steps: &HashMap<Fact, Step>) -> Option<Proof> {
+        let value = if facts.is_yes(fact) {
+            true
+        } else if facts.is_no(fact) {
+            false
+        } else {
+            return None;
+        };
+
+        let step = steps.get(&fact).cloned().unwrap_or(Step::Given);
+
+        let premises = match step {
+            Step::Given | Step::Answered => Vec::new(),
+            Step::Rule { ref premises, .. } => {
+                let mut keys = premises.yes.iter().chain(premises.no.iter()).collect::<Vec<_>>();
+                keys.sort_by_key(|fact| fact.name());
+
+                keys.iter().filter_map(|&fact| Proof::build(fact, facts, steps)).collect()
+            }
+        };
+
+        Some(Proof { fact, value, step, premises })
+    }
+
+    /// First step in the tree that picked one of several possible outcomes
+    pub fn first_assumption(&self) -> Option<&Step> {
+        if let Step::Rule { forced: false, .. } = self.step {
+            return Some(&self.step);
+        }
+
+        self.premises.iter().filter_map(|premise| premise.first_assumption()).next()
+    }
+
+    fn fmt_level(&self, f: &mut fmt::Formatter, level: usize) -> fmt::Result {
+        write!(f, "{}{} is {}", "  ".repeat(level), self.fact, self.value)?;
+
+        match self.step {
+            Step::Given => writeln!(f, ", given")?,
+            Step::Answered => writeln!(f, ", answered")?,
+            Step::Rule { ref rule, ref outcome, forced: true, .. } => {
+                writeln!(f, " by {} choosing {}", rule, outcome)?
+            }
+            Step::Rule { ref rule, ref outcome, forced: false, .. } => {
+                writeln!(f, " by {} assuming {}", rule, outcome)?
+            }
+        }
+
+        for premise in self.premises.iter() {
+            premise.fmt_level(f, level + 1)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Proof {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_level(f, 0)
+    }
+}
@@ -1,10 +1,11 @@
 use std::fmt;
+use std::mem;
 
-use super::Facts;
+use super::{ Assignment, Error, Fact, Facts };
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Rule {
-    Char(char),
+    Fact(Fact),
     Not(Box<Rule>),
     And(Box<Rule>, Box<Rule>),
     Or(Box<Rule>, Box<Rule>),
@@ -14,13 +15,18 @@ pub enum Rule {
 }
 
 impl Rule {
-    pub fn can_take(&self, facts: &Facts) -> bool {
+    /// Whether this is `=>` or `<=>` rule rather than a plain expression
+    pub fn is_rule(&self) -> bool {
+        matches!(self, Rule::IfThen(..) | Rule::IfAndOnlyIf(..))
+    }
+
+    pub fn can_take(&self, facts: &Facts) -> Result<bool, Error> {
         use Rule::*;
 
         match self {
-            IfThen(ref l, _) => l.can_take_recursive(facts),
-            IfAndOnlyIf(ref l, ref r) => l.can_take_recursive(facts) || r.can_take_recursive(facts),
-            _ => unreachable!(),
+            IfThen(ref l, _) => Ok(l.can_take_recursive(facts)),
+            IfAndOnlyIf(ref l, ref r) => Ok(l.can_take_recursive(facts) || r.can_take_recursive(facts)),
+            _ => Err(Error::NotARule(self.clone())),
         }
     }
 
@@ -28,22 +34,23 @@ impl Rule {
         use Rule::*;
 
         match self {
-            Char(ref c) => facts.is_yes(*c),
+            Fact(ref fact) => facts.is_yes(*fact),
             Not(ref l) => !l.can_take_recursive(facts),
             And(ref l, ref r) => l.can_take_recursive(facts) && r.can_take_recursive(facts),
             Or(ref l, ref r) => l.can_take_recursive(facts) || r.can_take_recursive(facts),
             Xor(ref l, ref r) => l.can_take_recursive(facts) ^ r.can_take_recursive(facts),
-            _ => unreachable!(),
+            IfThen(ref l, ref r) => !l.can_take_recursive(facts) || r.can_take_recursive(facts),
+            IfAndOnlyIf(ref l, ref r) => l.can_take_recursive(facts) == r.can_take_recursive(facts),
         }
     }
 
-    pub fn can_give(&self, facts: &Facts) -> bool {
+    pub fn can_give(&self, facts: &Facts) -> Result<bool, Error> {
         use Rule::*;
 
         match self {
-            IfThen(_, ref r) => r.can_give_recursive(facts),
-            IfAndOnlyIf(ref l, ref r) => l.can_give_recursive(facts) || r.can_give_recursive(facts),
-            _ => unreachable!(),
+            IfThen(_, ref r) => Ok(r.can_give_recursive(facts)),
+            IfAndOnlyIf(ref l, ref r) => Ok(l.can_give_recursive(facts) || r.can_give_recursive(facts)),
+            _ => Err(Error::NotARule(self.clone())),
         }
     }
 
@@ -51,71 +58,54 @@ impl Rule {
         use Rule::*;
 
         match self {
-            Char(ref c) => facts.is_no(*c) || facts.is_yes(*c),
+            Fact(ref fact) => facts.is_no(*fact) || facts.is_yes(*fact),
             Not(ref l) => l.can_give_recursive(facts),
             And(ref l, ref r) => l.can_give_recursive(facts) || r.can_give_recursive(facts),
             Or(ref l, ref r) => l.can_give_recursive(facts) || r.can_give_recursive(facts),
-            Xor(ref l, ref r) => l.can_give_recursive(facts) || r.can_give_recursive(facts),
-            _ => unreachable!(),
+            Xor(ref l, ref r) | IfThen(ref l, ref r) | IfAndOnlyIf(ref l, ref r) => {
+                l.can_give_recursive(facts) || r.can_give_recursive(facts)
+            }
         }
     }
 
-    pub fn possible_inputs(&self) -> Vec<Facts> {
+    pub fn possible_inputs(&self) -> Result<Vec<Facts>, Error> {
         use Rule::*;
 
         match self {
-            IfThen(ref l, _) => l.possible_combinations_recursive(),
+            IfThen(ref l, _) => Ok(l.possible_combinations_recursive()),
             IfAndOnlyIf(ref l, ref r) => {
                 let mut res = l.possible_combinations_recursive();
                 res.extend(r.possible_combinations_recursive());
-                res
+                Ok(res)
             },
-            _ => unreachable!(),
+            _ => Err(Error::NotARule(self.clone())),
         }
     }
 
-    pub fn possible_inputs_all(&self) -> Facts {
+    /// Every fact the premise mentions as a true fact, `<=>` rules have both sides as premises
+    pub fn possible_inputs_all(&self) -> Result<Facts, Error> {
         use Rule::*;
 
-        match self {
-            IfThen(ref l, _) => l.possible_inputs_all_recursive(),
-            IfAndOnlyIf(ref l, ref r) => {
-                l.possible_inputs_all_recursive().merge(&r.possible_inputs_all_recursive()).unwrap()
-            }
-            _ => unreachable!(),
-        }
-    }
-
-    fn possible_inputs_all_recursive(&self) -> Facts {
-        use Rule::*;
+        let facts = match self {
+            IfThen(ref l, _) => l.iter_facts().collect::<Vec<_>>(),
+            IfAndOnlyIf(ref l, ref r) => l.iter_facts().chain(r.iter_facts()).collect(),
+            _ => return Err(Error::NotARule(self.clone())),
+        };
 
-        match self {
-            Char(ref c) => Facts::new(&[*c], &[], &[]),
-            Not(ref l) => l.possible_inputs_all_recursive(),
-            And(ref l, ref r) => {
-                l.possible_inputs_all_recursive().merge(&r.possible_inputs_all_recursive()).unwrap()
-            }
-            Or(ref l, ref r) => {
-                l.possible_inputs_all_recursive().merge(&r.possible_inputs_all_recursive()).unwrap()
-            }
-            Xor(ref l, ref r) => {
-                l.possible_inputs_all_recursive().merge(&r.possible_inputs_all_recursive()).unwrap()
-            }
-            _ => unreachable!(),
-        }
+        Ok(Facts::new(&facts, &[], &[]))
     }
 
-    pub fn possible_outputs(&self) -> Vec<Facts> {
+    pub fn possible_outputs(&self) -> Result<Vec<Facts>, Error> {
         use Rule::*;
 
         match self {
-            IfThen(_, ref r) => r.possible_combinations_recursive(),
+            IfThen(_, ref r) => Ok(r.possible_combinations_recursive()),
             IfAndOnlyIf(ref l, ref r) => {
                 let mut res = l.possible_combinations_recursive();
                 res.extend(r.possible_combinations_recursive());
-                res
+                Ok(res)
             },
-            _ => unreachable!(),
+            _ => Err(Error::NotARule(self.clone())),
         }
     }
 
@@ -125,14 +115,15 @@ impl Rule {
         let mut res = Vec::new();
 
         match self {
-            Char(ref c) => {
-                let facts = Facts::new(&[*c], &[], &[]);
+            Fact(ref fact) => {
+                let facts = Facts::new(&[*fact], &[], &[]);
                 res.push(facts);
             }
+            Not(box Fact(ref fact)) => {
+                res.push(Facts::new(&[], &[*fact], &[]));
+            }
             Not(ref l) => {
-                for fact in l.possible_combinations_recursive() {
-                    res.push(fact.invert());
-                }
+                return l.negated().possible_combinations_recursive();
             }
             And(ref l, ref r) => {
                 let possible_l = l.possible_combinations_recursive();
@@ -169,16 +160,18 @@ impl Rule {
             Xor(ref l, ref r) => {
                 let possible_l = l.possible_combinations_recursive();
                 let possible_r = r.possible_combinations_recursive();
+                let possible_not_l = l.negated().possible_combinations_recursive();
+                let possible_not_r = r.negated().possible_combinations_recursive();
 
                 for p_l in possible_l.iter() {
-                    for p_r in possible_r.iter().map(|f| f.invert()) {
-                        if let Some(merged) = p_l.merge(&p_r) {
+                    for p_r in possible_not_r.iter() {
+                        if let Some(merged) = p_l.merge(p_r) {
                             res.push(merged);
                         }
                     }
                 }
 
-                for p_l in possible_l.iter().map(|f| f.invert()) {
+                for p_l in possible_not_l.iter() {
                     for p_r in possible_r.iter() {
                         if let Some(merged) = p_l.merge(p_r) {
                             res.push(merged);
@@ -186,58 +179,212 @@ impl Rule {
                     }
                 }
             }
-            _ => unreachable!(),
+            IfThen(ref l, ref r) => {
+                return Or(Box::new(l.negated()), r.clone()).possible_combinations_recursive();
+            }
+            IfAndOnlyIf(ref l, ref r) => {
+                return Xor(l.clone(), r.clone()).negated().possible_combinations_recursive();
+            }
         }
 
         res
     }
 
     /// Return list of possible outcomes or needed facts
-    pub fn try_match(&self, facts: &Facts) -> Option<Vec<Facts>> {
+    pub fn try_match(&self, facts: &Facts) -> Result<Option<Vec<Facts>>, Error> {
         use Rule::*;
 
         match self {
             IfThen(ref l, ref r) => {
                 if l.try_match_recursive(facts) {
-                    Some(r.possible_combinations_recursive())
+                    Ok(Some(r.possible_combinations_recursive()))
                 } else {
-                    None
+                    Ok(None)
                 }
             }
             IfAndOnlyIf(ref l, ref r) => {
                 if l.try_match_recursive(facts) {
-                    Some(r.possible_combinations_recursive())
+                    Ok(Some(r.possible_combinations_recursive()))
                 } else if r.try_match_recursive(facts) {
-                    Some(l.possible_combinations_recursive())
+                    Ok(Some(l.possible_combinations_recursive()))
                 } else {
-                    None
+                    Ok(None)
                 }
             }
-            _ => unreachable!(),
+            _ => Err(Error::NotARule(self.clone())),
         }
     }
 
     fn try_match_recursive(&self, facts: &Facts) -> bool {
+        self.value(facts) == Some(true)
+    }
+
+    /// Value of expression under `facts`, or `None` if it depends on unknown facts
+    pub fn value(&self, facts: &Facts) -> Option<bool> {
+        use Rule::*;
+
+        match self {
+            Fact(ref fact) => {
+                if facts.is_yes(*fact) {
+                    Some(true)
+                } else if facts.is_no(*fact) {
+                    Some(false)
+                } else {
+                    None
+                }
+            }
+            Not(ref l) => l.value(facts).map(|l| !l),
+            And(ref l, ref r) => match (l.value(facts), r.value(facts)) {
+                (Some(false), _) | (_, Some(false)) => Some(false),
+                (Some(true), Some(true)) => Some(true),
+                _ => None,
+            },
+            Or(ref l, ref r) => match (l.value(facts), r.value(facts)) {
+                (Some(true), _) | (_, Some(true)) => Some(true),
+                (Some(false), Some(false)) => Some(false),
+                _ => None,
+            },
+            Xor(ref l, ref r) => match (l.value(facts), r.value(facts)) {
+                (Some(l), Some(r)) => Some(l ^ r),
+                _ => None,
+            },
+            IfThen(ref l, ref r) => match (l.value(facts), r.value(facts)) {
+                (Some(false), _) | (_, Some(true)) => Some(true),
+                (Some(true), Some(false)) => Some(false),
+                _ => None,
+            },
+            IfAndOnlyIf(ref l, ref r) => match (l.value(facts), r.value(facts)) {
+                (Some(l), Some(r)) => Some(l == r),
+                _ => None,
+            },
+        }
+    }
+
+    /// Value under `assignment`, unlike `value` this works for `=>` and `<=>` the usual way
+    pub fn evaluate(&self, assignment: &Assignment) -> bool {
+        use Rule::*;
+
+        match self {
+            Fact(ref fact) => assignment.get(*fact),
+            Not(ref l) => !l.evaluate(assignment),
+            And(ref l, ref r) => l.evaluate(assignment) && r.evaluate(assignment),
+            Or(ref l, ref r) => l.evaluate(assignment) || r.evaluate(assignment),
+            Xor(ref l, ref r) => l.evaluate(assignment) != r.evaluate(assignment),
+            IfThen(ref l, ref r) => !l.evaluate(assignment) || r.evaluate(assignment),
+            IfAndOnlyIf(ref l, ref r) => l.evaluate(assignment) == r.evaluate(assignment),
+        }
+    }
+
+    /// Negation with `!` pushed down to facts, so that outcomes can be built from it
+    pub fn negated(&self) -> Rule {
+        use Rule::*;
+
+        match self {
+            Fact(_) => Not(Box::new(self.clone())),
+            Not(ref l) => *l.clone(),
+            And(ref l, ref r) => Or(Box::new(l.negated()), Box::new(r.negated())),
+            Or(ref l, ref r) => And(Box::new(l.negated()), Box::new(r.negated())),
+            Xor(ref l, ref r) => Or(
+                Box::new(And(l.clone(), r.clone())),
+                Box::new(And(Box::new(l.negated()), Box::new(r.negated()))),
+            ),
+            IfThen(ref l, ref r) => And(l.clone(), Box::new(r.negated())),
+            IfAndOnlyIf(ref l, ref r) => Xor(l.clone(), r.clone()),
+        }
+    }
+
+    /// `IfThen` rules the search can use in place of this one: a biconditional
+    /// works in both directions and, as its contrapositive, with both sides false
+    pub fn implications(&self) -> Result<Vec<Rule>, Error> {
         use Rule::*;
 
         match self {
-            Char(ref c) => facts.is_yes(*c),
-            Not(ref l) => l.try_match_recursive(&facts.invert()),
-            And(ref l, ref r) => l.try_match_recursive(facts) && r.try_match_recursive(facts),
-            Or(ref l, ref r) => l.try_match_recursive(facts) || r.try_match_recursive(facts),
-            Xor(ref l, ref r) => l.try_match_recursive(facts) ^ r.try_match_recursive(facts),
-            _ => unreachable!(),
+            IfThen(..) => Ok(vec![self.clone()]),
+            IfAndOnlyIf(ref l, ref r) => Ok(vec![
+                IfThen(l.clone(), r.clone()),
+                IfThen(r.clone(), l.clone()),
+                IfThen(Box::new(Not(l.clone())), Box::new(Not(r.clone()))),
+                IfThen(Box::new(Not(r.clone())), Box::new(Not(l.clone()))),
+            ]),
+            _ => Err(Error::NotARule(self.clone())),
+        }
+    }
+
+    /// Canonical form with double negations dropped, `!` pushed through `+` and `|`,
+    /// chains of one operator flattened and their operands sorted, and repeated operands
+    /// of `+` and `|` dropped. Rules with the same canonical form mean the same thing
+    pub fn normalized(&self) -> Rule {
+        use Rule::*;
+
+        match self {
+            Fact(..) => self.clone(),
+            Not(ref l) => match **l {
+                Not(ref l) => l.normalized(),
+                And(ref l, ref r) => Or(Box::new(Not(l.clone())), Box::new(Not(r.clone()))).normalized(),
+                Or(ref l, ref r) => And(Box::new(Not(l.clone())), Box::new(Not(r.clone()))).normalized(),
+                ref l => Not(Box::new(l.normalized())),
+            },
+            And(ref l, ref r) => self.normalized_chain(And, l, r, true),
+            Or(ref l, ref r) => self.normalized_chain(Or, l, r, true),
+            Xor(ref l, ref r) => self.normalized_chain(Xor, l, r, false),
+            IfThen(ref l, ref r) => IfThen(Box::new(l.normalized()), Box::new(r.normalized())),
+            IfAndOnlyIf(ref l, ref r) => {
+                let (l, r) = (l.normalized(), r.normalized());
+
+                if l.to_string() <= r.to_string() {
+                    IfAndOnlyIf(Box::new(l), Box::new(r))
+                } else {
+                    IfAndOnlyIf(Box::new(r), Box::new(l))
+                }
+            }
+        }
+    }
+
+    /// Whether `self` and `other` have the same canonical form
+    pub fn is_equivalent(&self, other: &Rule) -> bool {
+        self.normalized() == other.normalized()
+    }
+
+    /// `l` and `r` joined back by `op`, the operator of `self`, after normalizing them
+    fn normalized_chain(&self, op: fn(Box<Rule>, Box<Rule>) -> Rule, l: &Rule, r: &Rule, idempotent: bool) -> Rule {
+        let mut operands = Vec::new();
+        self.splice(&l.normalized(), &mut operands);
+        self.splice(&r.normalized(), &mut operands);
+
+        operands.sort_by_cached_key(|operand| operand.to_string());
+        if idempotent {
+            operands.dedup();
+        }
+
+        let mut operands = operands.into_iter();
+        let first = operands.next().unwrap();
+
+        operands.fold(first, |l, r| op(Box::new(l), Box::new(r)))
+    }
+
+    /// Push `operand` to `operands`, or its operands if it has the same operator as `self`
+    fn splice(&self, operand: &Rule, operands: &mut Vec<Rule>) {
+        use Rule::*;
+
+        match operand {
+            And(ref l, ref r) | Or(ref l, ref r) | Xor(ref l, ref r)
+                if mem::discriminant(operand) == mem::discriminant(self) =>
+            {
+                self.splice(l, operands);
+                self.splice(r, operands);
+            }
+            operand => operands.push(operand.clone()),
         }
     }
 
     /// Iterate over facts mentioned in this rule
-    pub fn iter_facts<'a>(&'a self) -> impl Iterator<Item=char> + 'a {
+    pub fn iter_facts<'a>(&'a self) -> impl Iterator<Item=Fact> + 'a {
         use Rule::*;
 
         let mut stack = Vec::with_capacity(8);
 
         match self {
-            Char(_) => stack.push(self),
+            Fact(_) => stack.push(self),
             Not(ref l) => stack.push(l.as_ref()),
             And(ref l, ref r) => { stack.push(l.as_ref()); stack.push(r.as_ref()); }
             Or(ref l, ref r) => { stack.push(l.as_ref()); stack.push(r.as_ref()); }
@@ -250,48 +397,55 @@ impl Rule {
     }
 }
 
+impl Rule {
+    /// Place in the grammar's precedence table, higher binds tighter
+    fn precedence(&self) -> u8 {
+        use Rule::*;
+
+        match self {
+            IfThen(..) | IfAndOnlyIf(..) => 0,
+            Xor(..) => 1,
+            Or(..) => 2,
+            And(..) => 3,
+            Fact(..) | Not(..) => 4,
+        }
+    }
+
+    /// Write `self` in place of an operand that binds at least as tight as `precedence`,
+    /// in parentheses if it doesn't
+    fn fmt_operand(&self, f: &mut fmt::Formatter, precedence: u8) -> fmt::Result {
+        if self.precedence() < precedence {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
+}
+
+/// Operators are left-associative, so a right operand of the same precedence keeps its parentheses
 impl fmt::Display for Rule {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         use Rule::*;
 
-        match self {
-            Char(ref c) => write!(f, "{}", c),
-            Not(box Char(ref c)) => write!(f, "!{}", c),
-            Not(ref l) => write!(f, "!({})", l),
-            And(ref l, ref r) => write!(f, "({} + {})", l, r),
-            Or(ref l, ref r) => write!(f, "({} | {})", l, r),
-            Xor(ref l, ref r) => write!(f, "({} ^ {})", l, r),
-            IfThen(ref l, ref r) => {
-                match l {
-                    box And(ref l, ref r) => write!(f, "{} + {}", l, r)?,
-                    box Or(ref l, ref r) => write!(f, "{} | {}", l, r)?,
-                    box Xor(ref l, ref r) => write!(f, "{} ^ {}", l, r)?,
-                    box x => write!(f, "{}", x)?,
-                }
-                write!(f, " => ")?;
-                match r {
-                    box And(ref l, ref r) => write!(f, "{} + {}", l, r),
-                    box Or(ref l, ref r) => write!(f, "{} | {}", l, r),
-                    box Xor(ref l, ref r) => write!(f, "{} ^ {}", l, r),
-                    box x => write!(f, "{}", x),
-                }
-            }
-            IfAndOnlyIf(ref l, ref r) => {
-                match l {
-                    box And(ref l, ref r) => write!(f, "{} + {}", l, r)?,
-                    box Or(ref l, ref r) => write!(f, "{} | {}", l, r)?,
-                    box Xor(ref l, ref r) => write!(f, "{} ^ {}", l, r)?,
-                    box x => write!(f, "{}", x)?,
-                }
-                write!(f, " <=> ")?;
-                match r {
-                    box And(ref l, ref r) => write!(f, "{} + {}", l, r),
-                    box Or(ref l, ref r) => write!(f, "{} | {}", l, r),
-                    box Xor(ref l, ref r) => write!(f, "{} ^ {}", l, r),
-                    box x => write!(f, "{}", x),
-                }
+        let (l, op, r) = match self {
+            Fact(ref fact) => return write!(f, "{}", fact),
+            Not(ref l) => {
+                write!(f, "!")?;
+                return l.fmt_operand(f, 4);
             }
-        }
+            And(ref l, ref r) => (l, " + ", r),
+            Or(ref l, ref r) => (l, " | ", r),
+            Xor(ref l, ref r) => (l, " ^ ", r),
+            IfThen(ref l, ref r) => (l, " => ", r),
+            IfAndOnlyIf(ref l, ref r) => (l, " <=> ", r),
+        };
+
+        let precedence = self.precedence();
+
+        // Sides of `=>` and `<=>` are whole expressions, but can't have arrows of their own
+        l.fmt_operand(f, precedence.max(1))?;
+        write!(f, "{}", op)?;
+        r.fmt_operand(f, precedence + 1)
     }
 }
 
@@ -300,23 +454,20 @@ pub struct RuleFactsIterator<'a> {
 }
 
 impl<'a> Iterator for RuleFactsIterator<'a> {
-    type Item = char;
+    type Item = Fact;
 
     fn next(&mut self) -> Option<Self::Item> {
         use Rule::*;
 
         loop {
-            if let Some(rule) = self.stack.pop() {
-                match rule {
-                    Char(ref c) => return Some(*c),
-                    Not(ref l) => { self.stack.push(l.as_ref()); continue; }
-                    And(ref l, ref r) => { self.stack.push(l.as_ref()); self.stack.push(r.as_ref()); continue; }
-                    Or(ref l, ref r) => { self.stack.push(l.as_ref()); self.stack.push(r.as_ref()); continue; }
-                    Xor(ref l, ref r) => { self.stack.push(l.as_ref()); self.stack.push(r.as_ref()); continue; }
-                    _ => unreachable!(),
-                }
-            } else {
-                return None;
+            match self.stack.pop()? {
+                Fact(ref fact) => return Some(*fact),
+                Not(ref l) => { self.stack.push(l.as_ref()); continue; }
+                And(ref l, ref r) => { self.stack.push(l.as_ref()); self.stack.push(r.as_ref()); continue; }
+                Or(ref l, ref r) => { self.stack.push(l.as_ref()); self.stack.push(r.as_ref()); continue; }
+                Xor(ref l, ref r) => { self.stack.push(l.as_ref()); self.stack.push(r.as_ref()); continue; }
+                IfThen(ref l, ref r) => { self.stack.push(l.as_ref()); self.stack.push(r.as_ref()); continue; }
+                IfAndOnlyIf(ref l, ref r) => { self.stack.push(l.as_ref()); self.stack.push(r.as_ref()); continue; }
             }
         }
     }
@@ -0,0 +1,399 @@
+use std::collections::HashMap;
+use std::ops::Not;
+use std::time::Instant;
+
+use super::{ Error, Fact, Facts, Limits, Rule };
+use super::error::Limit;
+
+/// Propositional variable or its negation
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Lit(u32);
+
+impl Lit {
+    fn new(var: usize, negative: bool) -> Lit {
+        Lit((var as u32) << 1 | negative as u32)
+    }
+
+    pub fn var(self) -> usize {
+        (self.0 >> 1) as usize
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.0 & 1 == 1
+    }
+
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl Not for Lit {
+    type Output = Lit;
+
+    fn not(self) -> Lit {
+        Lit(self.0 ^ 1)
+    }
+}
+
+/// Formula in conjunctive normal form, built from rules with Tseitin encoding
+#[derive(Clone, Debug, Default)]
+pub struct Cnf {
+    clauses: Vec<Vec<Lit>>,
+    facts: HashMap<Fact, Lit>,
+    num_vars: usize,
+}
+
+impl Cnf {
+    pub fn new() -> Cnf {
+        Cnf::default()
+    }
+
+    /// Build formula stating that all `rules` and `given` facts hold
+    pub fn from_rules<'a, I>(rules: I, given: &Facts) -> Cnf
+    where
+        I: IntoIterator<Item = &'a Rule>,
+    {
+        let mut cnf = Cnf::new();
+
+        for rule in rules {
+            cnf.add_rule(rule);
+        }
+
+        cnf.add_facts(given);
+        cnf
+    }
+
+    pub fn clauses(&self) -> &[Vec<Lit>] {
+        &self.clauses
+    }
+
+    pub fn num_vars(&self) -> usize {
+        self.num_vars
+    }
+
+    /// Literal that is true when `fact` is
+    pub fn fact(&mut self, fact: Fact) -> Lit {
+        if let Some(&lit) = self.facts.get(&fact) {
+            return lit;
+        }
+
+        let lit = self.fresh();
+        self.facts.insert(fact, lit);
+        lit
+    }
+
+    /// New variable that isn't tied to any fact
+    pub fn fresh(&mut self) -> Lit {
+        self.num_vars += 1;
+        Lit::new(self.num_vars - 1, false)
+    }
+
+    pub fn add_clause(&mut self, clause: Vec<Lit>) {
+        self.clauses.push(clause);
+    }
+
+    /// State that `rule` holds
+    pub fn add_rule(&mut self, rule: &Rule) {
+        let lit = self.encode(rule);
+        self.add_clause(vec![lit]);
+    }
+
+    /// State that `rule` holds whenever `guard` does
+    pub fn add_rule_guarded(&mut self, rule: &Rule, guard: Lit) {
+        let lit = self.encode(rule);
+        self.add_clause(vec![!guard, lit]);
+    }
+
+    /// State that true facts hold and false facts don't
+    pub fn add_facts(&mut self, facts: &Facts) {
+        for fact in facts.yes.iter() {
+            let lit = self.fact(fact);
+            self.add_clause(vec![lit]);
+        }
+
+        for fact in facts.no.iter() {
+            let lit = self.fact(fact);
+            self.add_clause(vec![!lit]);
+        }
+    }
+
+    /// Literal equivalent to `rule`, adding clauses that define it
+    fn encode(&mut self, rule: &Rule) -> Lit {
+        use Rule::*;
+
+        match rule {
+            Fact(ref fact) => self.fact(*fact),
+            Not(ref l) => !self.encode(l),
+            And(ref l, ref r) => {
+                let (a, b, x) = (self.encode(l), self.encode(r), self.fresh());
+                self.add_clause(vec![!x, a]);
+                self.add_clause(vec![!x, b]);
+                self.add_clause(vec![x, !a, !b]);
+                x
+            }
+            Or(ref l, ref r) => {
+                let (a, b, x) = (self.encode(l), self.encode(r), self.fresh());
+                self.add_clause(vec![!x, a, b]);
+                self.add_clause(vec![x, !a]);
+                self.add_clause(vec![x, !b]);
+                x
+            }
+            Xor(ref l, ref r) => {
+                let (a, b) = (self.encode(l), self.encode(r));
+                self.equivalence(a, !b)
+            }
+            IfThen(ref l, ref r) => {
+                let (a, b, x) = (self.encode(l), self.encode(r), self.fresh());
+                self.add_clause(vec![!x, !a, b]);
+                self.add_clause(vec![x, a]);
+                self.add_clause(vec![x, !b]);
+                x
+            }
+            IfAndOnlyIf(ref l, ref r) => {
+                let (a, b) = (self.encode(l), self.encode(r));
+                self.equivalence(a, b)
+            }
+        }
+    }
+
+    /// Literal that is true when `a` and `b` have the same value
+    fn equivalence(&mut self, a: Lit, b: Lit) -> Lit {
+        let x = self.fresh();
+        self.add_clause(vec![!x, !a, b]);
+        self.add_clause(vec![!x, a, !b]);
+        self.add_clause(vec![x, a, b]);
+        self.add_clause(vec![x, !a, !b]);
+        x
+    }
+
+    /// Find a model where all `assumptions` hold, or `None` if there is none
+    pub fn solve(&self, assumptions: &[Lit]) -> Option<Vec<bool>> {
+        // Without limits the search runs to the end
+        Dpll::new(self, None).solve(assumptions).unwrap_or_default()
+    }
+
+    pub fn is_satisfiable(&self) -> bool {
+        self.solve(&[]).is_some()
+    }
+
+    /// Same as `is_satisfiable`, giving up once the time limit counted from `started` runs out
+    /// or the search makes more decisions than the step limit allows
+    pub fn is_satisfiable_within(&self, limits: &Limits, started: Instant) -> Result<bool, Error> {
+        Dpll::new(self, Some((limits, started))).solve(&[]).map(|model| model.is_some())
+    }
+
+    /// Minimal subset of `assumptions` that can't hold together,
+    /// or `None` if all of them can
+    pub fn unsat_core(&self, assumptions: &[Lit]) -> Option<Vec<Lit>> {
+        if self.solve(assumptions).is_some() {
+            return None;
+        }
+
+        let mut core = assumptions.to_vec();
+        let mut idx = 0;
+
+        while idx < core.len() {
+            let mut rest = core.clone();
+            rest.remove(idx);
+
+            if self.solve(&rest).is_none() {
+                core = rest;
+            } else {
+                idx += 1;
+            }
+        }
+
+        Some(core)
+    }
+
+    /// Whether every model of the formula makes `lit` true
+    pub fn entails(&self, lit: Lit) -> bool {
+        self.solve(&[!lit]).is_none()
+    }
+}
+
+/// Backtracking search with unit propagation over two watched literals per clause
+struct Dpll {
+    clauses: Vec<Vec<Lit>>,
+    watches: Vec<Vec<usize>>,
+    values: Vec<Option<bool>>,
+    trail: Vec<Lit>,
+    /// Trail length before each decision, decided literal and whether it was already flipped
+    decisions: Vec<(usize, Lit, bool)>,
+    head: usize,
+    /// Limits to stop at, with the time they are counted from
+    limits: Option<(Limits, Instant)>,
+    decided: usize,
+}
+
+impl Dpll {
+    fn new(cnf: &Cnf, limits: Option<(&Limits, Instant)>) -> Dpll {
+        Dpll {
+            clauses: cnf.clauses.clone(),
+            watches: vec![Vec::new(); cnf.num_vars * 2],
+            values: vec![None; cnf.num_vars],
+            trail: Vec::new(),
+            decisions: Vec::new(),
+            head: 0,
+            limits: limits.map(|(limits, started)| (*limits, started)),
+            decided: 0,
+        }
+    }
+
+    fn check_limits(&mut self) -> Result<(), Error> {
+        self.decided += 1;
+
+        let (limits, started) = match self.limits {
+            Some(ref limits) => limits,
+            None => return Ok(()),
+        };
+
+        if let Some(steps) = limits.steps {
+            if self.decided > steps {
+                return Err(Error::LimitExceeded(Limit::Steps(steps)));
+            }
+        }
+
+        if let Some(time) = limits.time {
+            if started.elapsed() > time {
+                return Err(Error::LimitExceeded(Limit::Time(time)));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn value(&self, lit: Lit) -> Option<bool> {
+        self.values[lit.var()].map(|value| value != lit.is_negative())
+    }
+
+    /// Make `lit` true, returning `false` if it's already false
+    fn enqueue(&mut self, lit: Lit) -> bool {
+        match self.value(lit) {
+            Some(value) => value,
+            None => {
+                self.values[lit.var()] = Some(!lit.is_negative());
+                self.trail.push(lit);
+                true
+            }
+        }
+    }
+
+    /// Assign everything implied by the trail, returning `false` on conflict
+    fn propagate(&mut self) -> bool {
+        while self.head < self.trail.len() {
+            let false_lit = !self.trail[self.head];
+            self.head += 1;
+
+            let mut watching = std::mem::take(&mut self.watches[false_lit.index()]);
+            let mut idx = 0;
+            let mut conflict = false;
+
+            while idx < watching.len() {
+                let ci = watching[idx];
+
+                if self.clauses[ci][0] == false_lit {
+                    self.clauses[ci].swap(0, 1);
+                }
+
+                let first = self.clauses[ci][0];
+                if self.value(first) == Some(true) {
+                    idx += 1;
+                    continue;
+                }
+
+                let replacement = (2..self.clauses[ci].len())
+                    .find(|&k| self.value(self.clauses[ci][k]) != Some(false));
+
+                if let Some(k) = replacement {
+                    self.clauses[ci].swap(1, k);
+                    let lit = self.clauses[ci][1];
+                    self.watches[lit.index()].push(ci);
+                    watching.swap_remove(idx);
+                    continue;
+                }
+
+                if !self.enqueue(first) {
+                    conflict = true;
+                    break;
+                }
+
+                idx += 1;
+            }
+
+            self.watches[false_lit.index()] = watching;
+
+            if conflict {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn undo(&mut self, len: usize) {
+        for lit in self.trail.drain(len..) {
+            self.values[lit.var()] = None;
+        }
+
+        self.head = len;
+    }
+
+    fn solve(mut self, assumptions: &[Lit]) -> Result<Option<Vec<bool>>, Error> {
+        for ci in 0..self.clauses.len() {
+            match self.clauses[ci].len() {
+                0 => return Ok(None),
+                1 => {
+                    let lit = self.clauses[ci][0];
+                    if !self.enqueue(lit) {
+                        return Ok(None);
+                    }
+                }
+                _ => {
+                    let (a, b) = (self.clauses[ci][0], self.clauses[ci][1]);
+                    self.watches[a.index()].push(ci);
+                    self.watches[b.index()].push(ci);
+                }
+            }
+        }
+
+        for &lit in assumptions.iter() {
+            if !self.enqueue(lit) {
+                return Ok(None);
+            }
+        }
+
+        if !self.propagate() {
+            return Ok(None);
+        }
+
+        loop {
+            let var = match self.values.iter().position(|value| value.is_none()) {
+                Some(var) => var,
+                None => return Ok(Some(self.values.iter().map(|value| value.unwrap()).collect())),
+            };
+
+            self.check_limits()?;
+
+            let lit = Lit::new(var, true);
+            self.decisions.push((self.trail.len(), lit, false));
+            self.enqueue(lit);
+
+            while !self.propagate() {
+                loop {
+                    let (len, lit, flipped) = match self.decisions.pop() {
+                        Some(decision) => decision,
+                        None => return Ok(None),
+                    };
+                    self.undo(len);
+
+                    if !flipped {
+                        self.decisions.push((len, !lit, true));
+                        self.enqueue(!lit);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
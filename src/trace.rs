@@ -0,0 +1,61 @@
+use super::{ Fact, Facts, Rule };
+
+/// Step of the solver's reasoning, reported to an `Observer`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event<'a> {
+    /// Solver descended to `depth` with these working facts
+    Entered { depth: usize, facts: &'a Facts },
+    /// Rule concludes some unknown fact and is going to be used
+    RuleTried { depth: usize, rule: &'a Rule, facts: &'a Facts },
+    /// Rule premise holds, `outcomes` are the ways its conclusion can be satisfied
+    Outcomes { depth: usize, rule: &'a Rule, outcomes: &'a [Facts] },
+    /// One of the outcomes is going to be merged with working facts
+    OutcomeTried { depth: usize, rule: &'a Rule, outcome: &'a Facts },
+    /// Outcome doesn't make any unknown fact known
+    NoProgress { depth: usize, rule: &'a Rule, outcome: &'a Facts },
+    /// Outcome contradicts working facts
+    Conflict { depth: usize, rule: &'a Rule, outcome: &'a Facts },
+    /// Rule premise can't be established on this branch
+    NoMatch { depth: usize, rule: &'a Rule, facts: &'a Facts },
+    /// Rule premise depends on facts already being looked for, so it isn't expanded
+    Cycle { depth: usize, rule: &'a Rule, facts: &'a Facts },
+    /// Consultant was asked about a fact no rule concludes
+    Asked { depth: usize, fact: Fact, answer: Option<bool> },
+    /// Nothing is unknown anymore
+    Resolved { depth: usize, facts: &'a Facts },
+}
+
+impl<'a> Event<'a> {
+    pub fn depth(&self) -> usize {
+        use Event::*;
+
+        match *self {
+            Entered { depth, .. } => depth,
+            RuleTried { depth, .. } => depth,
+            Outcomes { depth, .. } => depth,
+            OutcomeTried { depth, .. } => depth,
+            NoProgress { depth, .. } => depth,
+            Conflict { depth, .. } => depth,
+            NoMatch { depth, .. } => depth,
+            Cycle { depth, .. } => depth,
+            Asked { depth, .. } => depth,
+            Resolved { depth, .. } => depth,
+        }
+    }
+}
+
+/// Receiver of solver events
+pub trait Observer {
+    fn event(&mut self, event: &Event);
+}
+
+/// Stay silent
+impl Observer for () {
+    fn event(&mut self, _: &Event) {}
+}
+
+impl<F: FnMut(&Event)> Observer for F {
+    fn event(&mut self, event: &Event) {
+        self(event)
+    }
+}
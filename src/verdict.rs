@@ -0,0 +1,67 @@
+use std::fmt;
+
+use super::{ Fact, Rule };
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Truth {
+    True,
+    False,
+    Undetermined,
+}
+
+/// Why a fact got its truth value
+#[derive(Clone, Debug, PartialEq)]
+pub enum Reason {
+    /// Fact was given initially
+    Given,
+    /// Consultant told the value of the fact
+    Answered,
+    /// Fact was derived by the rule
+    Derived(Rule),
+    /// Fact depends on which of several possible outcomes of the rule holds
+    Ambiguous(Rule),
+    /// Nothing could establish the fact, so it's false by default
+    Default,
+    /// Every model of the rules and given facts agrees on the value
+    Entailed,
+    /// Some models make the fact true and some false
+    Independent,
+}
+
+/// Answer about a single queried fact
+#[derive(Clone, Debug, PartialEq)]
+pub struct Verdict {
+    pub fact: Fact,
+    pub truth: Truth,
+    pub reason: Reason,
+}
+
+impl fmt::Display for Truth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Truth::True => write!(f, "true"),
+            Truth::False => write!(f, "false"),
+            Truth::Undetermined => write!(f, "undetermined"),
+        }
+    }
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Reason::Given => write!(f, "given"),
+            Reason::Answered => write!(f, "answered"),
+            Reason::Derived(ref rule) => write!(f, "by {}", rule),
+            Reason::Ambiguous(ref rule) => write!(f, "depends on the outcome of {}", rule),
+            Reason::Default => write!(f, "by default"),
+            Reason::Entailed => write!(f, "entailed"),
+            Reason::Independent => write!(f, "both values are consistent"),
+        }
+    }
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} is {} ({})", self.fact, self.truth, self.reason)
+    }
+}
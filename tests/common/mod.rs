@@ -0,0 +1,31 @@
+//! Strategies generating facts, expressions and rules for property tests
+
+use expert_system::{ Fact, Rule };
+use proptest::prelude::*;
+
+pub fn fact(names: &[&'static str]) -> impl Strategy<Value = Rule> {
+    prop::sample::select(names.to_vec()).prop_map(|name| Rule::Fact(Fact::new(name)))
+}
+
+/// Expressions that may be sides of `=>` and `<=>`, nested at most `depth` levels
+/// and made of about `size` facts
+pub fn expr(names: &[&'static str], depth: u32, size: u32) -> impl Strategy<Value = Rule> {
+    fact(names).prop_recursive(depth, size, 2, |inner| {
+        prop_oneof![
+            inner.clone().prop_map(|l| Rule::Not(Box::new(l))),
+            (inner.clone(), inner.clone()).prop_map(|(l, r)| Rule::And(Box::new(l), Box::new(r))),
+            (inner.clone(), inner.clone()).prop_map(|(l, r)| Rule::Or(Box::new(l), Box::new(r))),
+            (inner.clone(), inner).prop_map(|(l, r)| Rule::Xor(Box::new(l), Box::new(r))),
+        ]
+    })
+}
+
+/// `=>` rules, and `<=>` rules a third as often, with sides like `expr` makes
+pub fn rule(names: &[&'static str], depth: u32, size: u32) -> impl Strategy<Value = Rule> {
+    let side = || expr(names, depth, size);
+
+    prop_oneof![
+        3 => (side(), side()).prop_map(|(l, r)| Rule::IfThen(Box::new(l), Box::new(r))),
+        1 => (side(), side()).prop_map(|(l, r)| Rule::IfAndOnlyIf(Box::new(l), Box::new(r))),
+    ]
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use expert_system::{ parser, Rule };
+use proptest::prelude::*;
+
+const FACTS: [&str; 5] = ["A", "B", "C", "x1", "has_fever"];
+
+fn rule() -> impl Strategy<Value = Rule> {
+    common::rule(&FACTS, 6, 64)
+}
+
+proptest! {
+    #[test]
+    fn display_parses_back(rule in rule()) {
+        prop_assert_eq!(parser::rul(&rule.to_string()).ok(), Some(rule));
+    }
+
+    #[test]
+    fn display_has_no_redundant_parentheses(rule in rule()) {
+        let text = rule.to_string();
+
+        // Dropping any pair of parentheses has to change the meaning or break the syntax
+        for (open, _) in text.match_indices('(') {
+            let mut depth = 0;
+            let close = open + text[open..].find(|c| {
+                depth += match c { '(' => 1, ')' => -1, _ => 0 };
+                depth == 0
+            }).unwrap();
+
+            let stripped = format!("{}{}{}", &text[..open], &text[open + 1..close], &text[close + 1..]);
+            prop_assert_ne!(parser::rul(&stripped).ok(), Some(rule.clone()), "{} is the same as {}", stripped, text);
+        }
+    }
+}
+
+#[test]
+fn display_follows_precedence() {
+    for &text in [
+        "A + B | C => D",
+        "A + (B | C) => D ^ !(E + F)",
+        "A ^ B ^ C => A ^ (B ^ C)",
+        "!(A | B) <=> !!C",
+        "(A | B) + C => A | B + C",
+    ].iter() {
+        assert_eq!(parser::rul(text).unwrap().to_string(), text);
+    }
+}
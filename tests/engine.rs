@@ -0,0 +1,546 @@
+use std::collections::HashMap;
+use std::time::{ Duration, Instant };
+
+use expert_system::{
+    parse, parser, Answer, Assignment, Error, Event, Fact, FactInfo, Facts, KnowledgeBase, Limit, Limits, Mode,
+    Observer, Proof, Query, Reason, Rule, Step, Truth,
+};
+
+fn load(lines: &[&str]) -> KnowledgeBase {
+    let mut kb = KnowledgeBase::new();
+
+    for line in lines.iter() {
+        match parser::query(line).unwrap() {
+            Query::Rule(rule) => { kb.add_rule(rule).unwrap(); }
+            Query::Given(given, constraints) => {
+                kb.set_given(given).unwrap();
+                kb.set_constraints(constraints);
+            }
+            Query::Describe(fact, info) => kb.describe(fact, info),
+            Query::Limit(limit) => {
+                let mut limits = *kb.limits();
+                limits.set(limit);
+                kb.set_limits(limits);
+            }
+            Query::Mode(mode) => kb.set_mode(mode),
+            Query::Empty => {}
+            query => panic!("unexpected {:?}", query),
+        }
+    }
+
+    kb
+}
+
+fn truth(kb: &KnowledgeBase, find: &str) -> Truth {
+    let verdicts = kb.ask(&parser::find(find).unwrap(), &mut ()).unwrap();
+    assert_eq!(verdicts.len(), 1);
+    verdicts[0].truth
+}
+
+#[test]
+fn biconditional_left_to_right() {
+    let kb = load(&["A <=> B", "=A"]);
+    assert_eq!(truth(&kb, "?B"), Truth::True);
+}
+
+#[test]
+fn biconditional_right_to_left() {
+    let kb = load(&["A <=> B", "=B"]);
+    assert_eq!(truth(&kb, "?A"), Truth::True);
+}
+
+#[test]
+fn biconditional_contrapositive() {
+    let kb = load(&["C => !B", "A <=> B", "=C"]);
+    assert_eq!(truth(&kb, "?A"), Truth::False);
+
+    let kb = load(&["C => !A", "A <=> B", "=C"]);
+    assert_eq!(truth(&kb, "?B"), Truth::False);
+}
+
+#[test]
+fn biconditional_with_compound_sides() {
+    let kb = load(&["A + B <=> C", "=A B"]);
+    assert_eq!(truth(&kb, "?C"), Truth::True);
+
+    let kb = load(&["A + B <=> C", "D => !C", "=A D"]);
+    assert_eq!(truth(&kb, "?B"), Truth::False);
+}
+
+#[test]
+fn biconditional_agrees_with_exact_mode() {
+    let mut kb = load(&["A <=> B", "B <=> C", "=A"]);
+    assert_eq!(truth(&kb, "?C"), Truth::True);
+
+    kb.set_mode(Mode::Exact);
+    assert_eq!(truth(&kb, "?C"), Truth::True);
+}
+
+#[test]
+fn negated_given_drives_reasoning() {
+    let kb = load(&["!C => D", "=!C"]);
+    assert_eq!(truth(&kb, "?D"), Truth::True);
+
+    let kb = load(&["A <=> B", "=!A"]);
+    assert_eq!(truth(&kb, "?B"), Truth::False);
+}
+
+#[test]
+fn constraint_given() {
+    let kb = load(&["X => Z", "=!Y (X ^ Y)"]);
+    assert_eq!(truth(&kb, "?Z"), Truth::True);
+}
+
+#[test]
+fn given_items_need_no_separator_before_negation_or_parentheses() {
+    let (given, constraints) = parser::given("=A!B").unwrap();
+    assert_eq!(given, Facts::new(&[Fact::new("A")], &[Fact::new("B")], &[]));
+    assert!(constraints.is_empty());
+
+    let (given, constraints) = parser::given("=(A|B)!C").unwrap();
+    assert_eq!(given, Facts::new(&[], &[Fact::new("C")], &[]));
+    assert_eq!(constraints, vec![Rule::Or(Box::new(Rule::Fact(Fact::new("A"))), Box::new(Rule::Fact(Fact::new("B"))))]);
+
+    let find = parser::find("?A!B").unwrap();
+    assert_eq!(find, Facts::new(&[], &[], &[Fact::new("A"), Fact::new("B")]));
+}
+
+#[test]
+fn given_fact_both_true_and_false_is_contradiction() {
+    let (given, _) = parser::given("=A !A").unwrap();
+    let mut kb = KnowledgeBase::new();
+
+    assert!(matches!(kb.set_given(given), Err(Error::Contradiction)));
+    assert!(kb.given().is_empty(true, true, true));
+}
+
+#[test]
+fn violated_constraint_is_contradiction() {
+    let find = parser::find("?A B").unwrap();
+
+    let kb = load(&["=(A ^ B) !A !B"]);
+    assert!(matches!(kb.ask(&find, &mut ()), Err(Error::Contradiction)));
+
+    let kb = load(&["=(A + !A)"]);
+    assert!(matches!(kb.ask(&find, &mut ()), Err(Error::Contradiction)));
+}
+
+#[test]
+fn contradiction_follows_changes_to_rules_and_facts() {
+    let mut kb = load(&["A => B", "=A"]);
+    let find = parser::find("?B").unwrap();
+    assert!(kb.query(&find).is_ok());
+
+    kb.add_rule(parser::rul("A => !B").unwrap()).unwrap();
+    assert_eq!(kb.query(&find), Err(Error::Contradiction));
+
+    kb.set_given(Facts::default()).unwrap();
+    assert!(kb.query(&find).is_ok());
+
+    kb.set_constraints(vec![parser::rul("A => C").unwrap(), Rule::Fact(Fact::new("A"))]);
+    assert_eq!(kb.query(&find), Err(Error::Contradiction));
+}
+
+#[test]
+fn cyclic_rules_terminate() {
+    let kb = load(&["A => B", "B => A"]);
+    assert_eq!(truth(&kb, "?A"), Truth::False);
+
+    let kb = load(&["A => B", "B => C", "C => A", "=C"]);
+    assert_eq!(truth(&kb, "?B"), Truth::True);
+}
+
+/// Chain `F0 => F1`, `F1 => F2`, ... of `len` rules with `F0` given, asking about the last fact
+fn chain(len: usize) -> (KnowledgeBase, Facts) {
+    let mut kb = load(&["=F0"]);
+    for idx in 0..len {
+        kb.add_rule(parser::rul(&format!("F{} => F{}", idx, idx + 1)).unwrap()).unwrap();
+    }
+
+    (kb, parser::find(&format!("?F{}", len)).unwrap())
+}
+
+#[test]
+fn search_stops_at_limits() {
+    let (mut kb, find) = chain(10);
+    assert!(kb.query(&find).unwrap().is_yes(Fact::new("F10")));
+
+    kb.set_limits(Limits { depth: 3, ..Limits::default() });
+    assert_eq!(kb.query(&find), Err(Error::LimitExceeded(Limit::Depth(3))));
+
+    kb.set_limits(Limits { steps: Some(4), ..Limits::default() });
+    assert_eq!(kb.query(&find), Err(Error::LimitExceeded(Limit::Steps(4))));
+
+    kb.set_limits(Limits { time: Some(Duration::from_nanos(1)), ..Limits::default() });
+    assert_eq!(kb.query(&find), Err(Error::LimitExceeded(Limit::Time(Duration::from_nanos(1)))));
+}
+
+#[test]
+fn time_limit_bounds_consistency_check() {
+    // Nine pigeons in eight holes, one per hole, takes the SAT solver a long time to refute
+    let (pigeons, holes) = (9, 8);
+    let mut lines = vec!["limit time 100".to_string()];
+
+    for hole in 1..=holes {
+        for pigeon in 1..=pigeons {
+            for other in pigeon + 1..=pigeons {
+                lines.push(format!("P{}_{} => !P{}_{}", pigeon, hole, other, hole));
+            }
+        }
+    }
+
+    let somewhere = (1..=pigeons)
+        .map(|pigeon| {
+            let holes = (1..=holes).map(|hole| format!("P{}_{}", pigeon, hole)).collect::<Vec<_>>();
+            format!("({})", holes.join(" | "))
+        })
+        .collect::<Vec<_>>();
+    lines.push(format!("={}", somewhere.join(" ")));
+
+    let kb = load(&lines.iter().map(String::as_str).collect::<Vec<_>>());
+    let started = Instant::now();
+
+    assert_eq!(
+        kb.query(&parser::find("?Z").unwrap()),
+        Err(Error::LimitExceeded(Limit::Time(Duration::from_millis(100))))
+    );
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn check_reports_minimal_conflict() {
+    let kb = load(&["A => B", "B => C", "C => !A", "D => E", "E => !F", "=A D"]);
+    let conflict = kb.check().unwrap();
+
+    let rules = ["A => B", "B => C", "C => !A"].iter().map(|rule| parser::rul(rule).unwrap()).collect::<Vec<_>>();
+    assert_eq!(conflict.rules, rules);
+    assert_eq!(conflict.facts, Facts::new(&[Fact::new("A")], &[], &[]));
+
+    // Leaving out any rule or fact of the conflict makes the rest consistent
+    let consistent = |rules: &[Rule], given: &Facts| {
+        let mut kb = KnowledgeBase::new();
+        for rule in rules.iter() {
+            kb.add_rule(rule.clone()).unwrap();
+        }
+        kb.set_given(given.clone()).unwrap();
+        kb.check().is_none()
+    };
+
+    for idx in 0..conflict.rules.len() {
+        let mut rules = conflict.rules.clone();
+        rules.remove(idx);
+        assert!(consistent(&rules, &conflict.facts), "without {}", conflict.rules[idx]);
+    }
+
+    assert!(consistent(&conflict.rules, &Facts::default()));
+}
+
+#[test]
+fn check_accepts_consistent_knowledge_base() {
+    let kb = load(&["A => B | C", "B <=> !C", "C => D", "=A !B"]);
+    assert_eq!(kb.check(), None);
+
+    assert_eq!(KnowledgeBase::new().check(), None);
+}
+
+/// Observer keeping every event as a line like `1 tried A => B`
+#[derive(Default)]
+struct Recorder(Vec<String>);
+
+impl Observer for Recorder {
+    fn event(&mut self, event: &Event) {
+        let line = match *event {
+            Event::Entered { facts, .. } => format!("entered {}", facts),
+            Event::RuleTried { rule, .. } => format!("tried {}", rule),
+            Event::Outcomes { rule, outcomes, .. } => format!("{} outcomes of {}", outcomes.len(), rule),
+            Event::OutcomeTried { outcome, .. } => format!("outcome {}", outcome),
+            Event::NoProgress { outcome, .. } => format!("no progress {}", outcome),
+            Event::Conflict { outcome, .. } => format!("conflict {}", outcome),
+            Event::NoMatch { rule, .. } => format!("no match {}", rule),
+            Event::Cycle { rule, .. } => format!("cycle {}", rule),
+            Event::Asked { fact, .. } => format!("asked {}", fact),
+            Event::Resolved { facts, .. } => format!("resolved {}", facts),
+        };
+
+        self.0.push(format!("{} {}", event.depth(), line));
+    }
+}
+
+#[test]
+fn observer_hears_every_step() {
+    let kb = load(&["A => B", "B => C", "=A"]);
+    let mut recorder = Recorder::default();
+
+    kb.query_with(&parser::find("?C").unwrap(), &mut recorder).unwrap();
+
+    assert_eq!(recorder.0, vec![
+        "0 entered ( true: A  unknown: C )",
+        "0 tried B => C",
+        "1 entered ( true: A  unknown: B, C )",
+        "1 tried A => B",
+        "1 1 outcomes of A => B",
+        "1 outcome ( true: B )",
+        "2 entered ( true: A, B  unknown: C )",
+        "2 tried B => C",
+        "2 1 outcomes of B => C",
+        "2 outcome ( true: C )",
+        "3 entered ( true: A, B, C )",
+        "3 resolved ( true: A, B, C )",
+    ]);
+}
+
+#[test]
+fn explain_gives_proof_steps_and_reasons() {
+    let kb = load(&["A => B", "B => C", "=A"]);
+    let (a, b, c, d) = (Fact::new("A"), Fact::new("B"), Fact::new("C"), Fact::new("D"));
+
+    let given = Proof { fact: a, value: true, step: Step::Given, premises: Vec::new() };
+    let step = |rule: &str, premise: Fact, fact: Fact| Step::Rule {
+        rule: parser::rul(rule).unwrap(),
+        premises: Facts::new(&[premise], &[], &[]),
+        outcome: Facts::new(&[fact], &[], &[]),
+        forced: true,
+    };
+    let derived = Proof {
+        fact: c,
+        value: true,
+        step: step("B => C", b, c),
+        premises: vec![Proof { fact: b, value: true, step: step("A => B", a, b), premises: vec![given.clone()] }],
+    };
+
+    assert_eq!(kb.explain(c).unwrap(), Some(derived));
+    assert_eq!(kb.explain(a).unwrap(), Some(given));
+    assert_eq!(kb.explain(d).unwrap(), None);
+
+    let reason = |find: &str| {
+        let verdicts = kb.ask(&parser::find(find).unwrap(), &mut ()).unwrap();
+        (verdicts[0].truth, verdicts[0].reason.clone())
+    };
+
+    assert_eq!(reason("?A"), (Truth::True, Reason::Given));
+    assert_eq!(reason("?C"), (Truth::True, Reason::Derived(parser::rul("B => C").unwrap())));
+    assert_eq!(reason("?D"), (Truth::False, Reason::Default));
+}
+
+#[test]
+fn consultant_answers_leaf_facts() {
+    let kb = load(&["fever + cough => flu"]);
+    let find = parser::find("?flu").unwrap();
+
+    let mut asked = Vec::new();
+    let answer = kb
+        .consult(&find, &mut (), &mut |fact: Fact, _: Option<&FactInfo>| {
+            asked.push(fact.to_string());
+            Some(true)
+        })
+        .unwrap();
+
+    asked.sort();
+    assert_eq!(asked, vec!["cough", "fever"]);
+    assert_eq!(answer.verdict(Fact::new("flu")).truth, Truth::True);
+}
+
+#[test]
+fn derive_fires_rules_forward() {
+    let kb = load(&["A => B | C", "B <=> E", "=A !C"]);
+    let answer = kb.derive().unwrap();
+
+    assert!(answer.facts.is_yes(Fact::new("B")));
+    assert!(answer.facts.is_yes(Fact::new("E")));
+    assert!(answer.facts.is_no(Fact::new("C")));
+}
+
+#[test]
+fn derive_keeps_ambiguous_outcomes_unknown() {
+    let kb = load(&["A => B | C", "=A"]);
+    let answer = kb.derive().unwrap();
+
+    assert!(!answer.facts.is_yes(Fact::new("B")) && !answer.facts.is_no(Fact::new("B")));
+}
+
+#[test]
+fn saved_knowledge_base_round_trips() {
+    let kb = load(&[
+        "fact A \"patient has \\\"fever\\\"\" ask \"Fever?\"",
+        "fact B",
+        "A + (B | C) => D ^ !(E + F)",
+        "!(A | B) <=> C",
+        "=A !C B (X ^ Y) (!(P + Q)) (X => Y) (P <=> !Q) (R)",
+        "limit steps 500",
+        "mode exact",
+    ]);
+
+    let text = kb.to_string();
+    let loaded = load(&text.lines().collect::<Vec<_>>());
+
+    assert_eq!(loaded.to_string(), text);
+    assert_eq!(loaded.rules(), kb.rules());
+    assert_eq!(loaded.given(), kb.given());
+    assert_eq!(loaded.constraints(), kb.constraints());
+    assert_eq!(loaded.info(), kb.info());
+    assert_eq!(loaded.limits(), kb.limits());
+    assert_eq!(loaded.mode(), kb.mode());
+}
+
+#[test]
+fn rules_keep_their_ids() {
+    let mut kb = load(&["A => B", "B => C", "C => D"]);
+
+    assert_eq!(kb.remove_rule_by_id(2), Some(parser::rul("B => C").unwrap()));
+    assert_eq!(kb.add_rule(parser::rul("D => E").unwrap()).unwrap(), Some(4));
+    assert_eq!(kb.rules().keys().cloned().collect::<Vec<_>>(), vec![1, 3, 4]);
+}
+
+#[test]
+fn delete_matches_equivalent_rules() {
+    let mut kb = load(&["A + B => C", "!(A | B) => D", "B <=> E + F", "A => C + B"]);
+
+    for (rule, id) in [("B + A => C", 1), ("!!(!B + !A) => D", 2), ("F + E <=> B", 3), ("A => B + C + B", 4)].iter() {
+        assert_eq!(kb.lookup(&parser::rul(rule).unwrap()), vec![*id]);
+    }
+
+    assert!(kb.lookup(&parser::rul("C => A + B").unwrap()).is_empty());
+    assert_eq!(kb.remove_equivalent(&parser::rul("B + A => C").unwrap()).len(), 1);
+    assert!(kb.rules().get(&1).is_none());
+}
+
+#[test]
+fn evaluate_covers_implications() {
+    let rule = match parser::query("truth A + !B => C").unwrap() {
+        Query::Truth(rule) => rule,
+        query => panic!("unexpected {:?}", query),
+    };
+    let mut assignment = Assignment::new();
+
+    assignment.set(Fact::new("A"), true);
+    assert!(!rule.evaluate(&assignment));
+
+    assignment.set(Fact::new("C"), true);
+    assert!(rule.evaluate(&assignment));
+
+    assignment.set(Fact::new("C"), false);
+    assignment.set(Fact::new("B"), true);
+    assert!(rule.evaluate(&assignment));
+
+    let rule = parser::rul("A <=> !A").unwrap();
+    assert!(!rule.evaluate(&assignment));
+}
+
+#[test]
+fn assignment_from_bits_needs_a_bit_per_fact() {
+    let facts = (0..65).map(|idx| Fact::new(&format!("F{}", idx))).collect::<Vec<_>>();
+
+    let assignment = Assignment::from_bits(&facts[..64], 1 << 63).unwrap();
+    assert!(assignment.get(facts[63]));
+    assert!(!assignment.get(facts[0]));
+
+    assert_eq!(Assignment::from_bits(&facts, u64::MAX), None);
+}
+
+#[test]
+fn graph_highlights_proof() {
+    let kb = load(&["A => C", "B | !D => E", "=A"]);
+
+    let plain = kb.graph().to_string();
+    assert!(plain.contains("\"B\" -> \"#2\" [color=blue];"));
+    assert!(plain.contains("\"D\" -> \"#2\" [color=blue, style=dashed];"));
+    assert!(!plain.contains("fillcolor"));
+
+    let find = parser::find("?C").unwrap();
+    let answer = kb.solve(&find, &mut ()).unwrap();
+    let highlighted = kb.graph().highlight(&answer, &find).to_string();
+
+    assert!(highlighted.contains("\"#1\" [shape=box, label=\"#1 A => C\", style=filled, fillcolor=yellow];"));
+    assert!(highlighted.contains("\"A\" -> \"#1\" [penwidth=3];"));
+    assert!(!highlighted.contains("\"#2\" [shape=box, label=\"#2 B | !D => E\", style=filled"));
+}
+
+#[test]
+fn graph_highlights_only_edges_steps_used() {
+    let kb = load(&["A => B | C", "A => C", "=A"]);
+    let (a, b, c) = (Fact::new("A"), Fact::new("B"), Fact::new("C"));
+
+    let step = |rule: &str, fact: Fact| Step::Rule {
+        rule: parser::rul(rule).unwrap(),
+        premises: Facts::new(&[a], &[], &[]),
+        outcome: Facts::new(&[fact], &[], &[]),
+        forced: false,
+    };
+    let mut steps = HashMap::new();
+    steps.insert(b, step("A => B | C", b));
+    steps.insert(c, step("A => C", c));
+
+    let answer = Answer { facts: Facts::new(&[a, b, c], &[], &[]), steps };
+    let highlighted = kb.graph().highlight(&answer, &Facts::new(&[], &[], &[b, c])).to_string();
+
+    assert!(highlighted.contains("\"#1\" -> \"B\" [color=blue, penwidth=3];"));
+    assert!(highlighted.contains("\"#1\" -> \"C\" [color=blue];"));
+    assert!(highlighted.contains("\"#2\" -> \"C\" [penwidth=3];"));
+}
+
+#[test]
+fn expressions_are_not_rules() {
+    let expr = Rule::Or(Box::new(Rule::Fact(Fact::new("A"))), Box::new(Rule::Fact(Fact::new("B"))));
+    let mut kb = KnowledgeBase::new();
+
+    assert!(matches!(kb.add_rule(expr.clone()), Err(Error::NotARule(_))));
+    assert!(matches!(expr.try_match(&Facts::default()), Err(Error::NotARule(_))));
+    assert!(matches!(expr.implications(), Err(Error::NotARule(_))));
+    assert!(kb.rules().is_empty());
+}
+
+#[test]
+fn facts_sort_by_id_and_display_by_name() {
+    let (late, early) = (Fact::new("zz_interned_first"), Fact::new("aa_interned_second"));
+    assert!(late < early);
+
+    let facts = Facts::new(&[late, early], &[], &[]);
+    assert_eq!(facts.to_string(), "( true: aa_interned_second, zz_interned_first )");
+}
+
+#[test]
+fn comments_are_skipped() {
+    assert_eq!(parser::query("A => B # note").unwrap(), Query::Rule(parser::rul("A => B").unwrap()));
+    assert_eq!(parser::query("=A !B # given").unwrap(), parser::query("=A !B").unwrap());
+    assert_eq!(parser::query("?A#asked").unwrap(), parser::query("?A").unwrap());
+
+    for line in ["# only a comment", "   # indented", "#", ""].iter() {
+        assert_eq!(parser::query(line).unwrap(), Query::Empty, "{:?}", line);
+    }
+}
+
+#[test]
+fn parse_error_spans_offending_token() {
+    match parse("A + => B") {
+        Err(Error::Parse { span, .. }) => assert_eq!((span.start, span.end), (4, 6)),
+        result => panic!("unexpected {:?}", result),
+    }
+
+    assert!(matches!(parse("A => B"), Ok(Query::Rule(_))));
+}
+
+#[test]
+fn error_is_a_std_error() {
+    fn need_error<T: std::error::Error + Send + Sync + 'static>() {}
+    need_error::<Error>();
+
+    let e = KnowledgeBase::new().save("no/such/dir/kb.txt").unwrap_err();
+    let source = std::error::Error::source(&e).unwrap();
+
+    assert!(source.downcast_ref::<std::io::Error>().is_some());
+    assert_eq!(e.clone(), e);
+}
+
+#[test]
+fn knowledge_base_can_move_between_threads() {
+    fn need_send_sync<T: Send + Sync>() {}
+    need_send_sync::<KnowledgeBase>();
+
+    let kb = load(&["A => B", "=A"]);
+    kb.query(&parser::find("?B").unwrap()).unwrap();
+
+    let verdicts = std::thread::spawn(move || kb.ask(&parser::find("?B").unwrap(), &mut ()).unwrap())
+        .join()
+        .unwrap();
+    assert_eq!(verdicts[0].truth, Truth::True);
+}
@@ -0,0 +1,101 @@
+mod common;
+
+use expert_system::{ Error, Fact, Facts, KnowledgeBase, Mode, Reason, Truth };
+use proptest::prelude::*;
+
+const FACTS: [&str; 4] = ["A", "B", "C", "D"];
+
+/// Knowledge base of a few rules, some facts given true or false and maybe a constraint
+fn kb() -> impl Strategy<Value = KnowledgeBase> {
+    let rules = prop::collection::vec(common::rule(&FACTS, 2, 8), 1..4);
+    let given = prop::collection::vec(prop::option::of(any::<bool>()), FACTS.len());
+    let constraints = prop::collection::vec(common::expr(&FACTS, 2, 8), 0..2);
+
+    (rules, given, constraints).prop_map(|(rules, given, constraints)| {
+        let mut kb = KnowledgeBase::new();
+
+        for rule in rules {
+            kb.add_rule(rule).unwrap();
+        }
+
+        let mut facts = Facts::default();
+        for (name, value) in FACTS.iter().zip(given) {
+            match value {
+                Some(true) => { facts.yes.insert(Fact::new(name)); }
+                Some(false) => { facts.no.insert(Fact::new(name)); }
+                None => {}
+            }
+        }
+
+        kb.set_given(facts).unwrap();
+        kb.set_constraints(constraints);
+        kb
+    })
+}
+
+fn find() -> Facts {
+    Facts::new(&[], &[], &FACTS.iter().map(|&name| Fact::new(name)).collect::<Vec<_>>())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(500))]
+
+    /// Exact mode decides entailment, so it has to give the same answers
+    #[test]
+    fn exact_mode_agrees_with_oracle(mut kb in kb()) {
+        kb.set_mode(Mode::Exact);
+        let table = kb.truth_table().unwrap();
+
+        match kb.ask(&find(), &mut ()) {
+            Ok(verdicts) => {
+                prop_assert!(table.is_consistent());
+
+                for verdict in verdicts {
+                    prop_assert_eq!(Some(verdict.truth), table.truth(verdict.fact), "{}", verdict.fact);
+                }
+            }
+            Err(_) => prop_assert!(!table.is_consistent()),
+        }
+    }
+
+    /// Search assumes false whatever it can't establish, but what it does establish
+    /// through rules has to follow from the knowledge base
+    #[test]
+    fn search_mode_establishes_only_entailed_facts(kb in kb()) {
+        let table = kb.truth_table().unwrap();
+        if !table.is_consistent() {
+            return Ok(());
+        }
+
+        match kb.ask(&find(), &mut ()) {
+            Ok(verdicts) => {
+                for verdict in verdicts {
+                    if verdict.truth != Truth::Undetermined && verdict.reason != Reason::Default {
+                        prop_assert_eq!(Some(verdict.truth), table.truth(verdict.fact), "{}", verdict);
+                    }
+                }
+            }
+            Err(Error::LimitExceeded(_)) => {}
+            Err(e) => prop_assert!(false, "{}", e),
+        }
+    }
+
+    /// Forward chaining only sets facts that follow, and only fails when nothing is consistent
+    #[test]
+    fn derive_sets_only_entailed_facts(kb in kb()) {
+        let table = kb.truth_table().unwrap();
+
+        match kb.derive() {
+            Ok(answer) => {
+                for fact in answer.facts.yes.iter() {
+                    prop_assert!(table.truth(fact).is_none_or(|truth| truth == Truth::True), "{}", fact);
+                }
+
+                for fact in answer.facts.no.iter() {
+                    prop_assert!(table.truth(fact).is_none_or(|truth| truth == Truth::False), "{}", fact);
+                }
+            }
+            Err(_) => prop_assert!(!table.is_consistent()),
+        }
+    }
+}
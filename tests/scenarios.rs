@@ -0,0 +1,259 @@
+//! Runs every knowledge base in `tests/scenarios` line by line, checking each query against
+//! the expectation on the line after it:
+//!
+//! ```text
+//! ?D K
+//! #expect D=true K=undetermined
+//! ```
+//!
+//! A query that fails is expected with `#expect error <message>`.
+//! `cargo test --test scenarios -- --bless` rewrites expectations to what queries give now.
+
+use std::{ env, fs, process };
+use std::path::Path;
+
+use expert_system::{ parser, Fact, Facts, KnowledgeBase, Query, Truth };
+
+const EXPECT: &str = "#expect";
+
+/// What a query gives
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    /// True, false and undetermined facts
+    Facts(Facts),
+    Error(String),
+}
+
+impl Outcome {
+    fn parse(text: &str) -> Result<Outcome, String> {
+        let text = text.trim();
+
+        // `error` has to be a word of its own, `error_rate=true` is a fact
+        if let Some(message) = text.strip_prefix("error") {
+            if message.is_empty() || message.starts_with(char::is_whitespace) {
+                return Ok(Outcome::Error(message.trim().to_string()));
+            }
+        }
+
+        let mut facts = Facts::default();
+
+        for item in text.split_whitespace() {
+            let mut parts = item.splitn(2, '=');
+            let (name, value) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+            let fact = parser::find(&format!("?{}", name)).map_err(|_| format!("bad fact name in `{}`", item))?;
+            let fact = fact.unknown.iter().next().ok_or_else(|| format!("bad fact name in `{}`", item))?;
+
+            match value {
+                "true" => facts.yes.insert(fact),
+                "false" => facts.no.insert(fact),
+                "undetermined" => facts.unknown.insert(fact),
+                _ => return Err(format!("`{}` should be true, false or undetermined", item)),
+            };
+        }
+
+        Ok(Outcome::Facts(facts))
+    }
+
+    /// Values of facts by name, like `D=true`
+    fn values(facts: &Facts) -> Vec<(Fact, &'static str)> {
+        let mut values = facts.yes.iter().map(|fact| (fact, "true"))
+            .chain(facts.no.iter().map(|fact| (fact, "false")))
+            .chain(facts.unknown.iter().map(|fact| (fact, "undetermined")))
+            .collect::<Vec<_>>();
+        values.sort_by_key(|&(fact, value)| (fact.name(), value));
+        values
+    }
+
+    /// Expectation line stating this outcome
+    fn expectation(&self) -> String {
+        match self {
+            Outcome::Facts(ref facts) => {
+                let values = Outcome::values(facts)
+                    .iter()
+                    .map(|(fact, value)| format!(" {}={}", fact, value))
+                    .collect::<String>();
+
+                format!("{}{}", EXPECT, values)
+            }
+            Outcome::Error(ref message) => format!("{} error {}", EXPECT, message),
+        }
+    }
+
+    /// Ways in which `actual` differs from `self`
+    fn diff(&self, actual: &Outcome) -> Vec<String> {
+        match (self, actual) {
+            (Outcome::Facts(ref expected), Outcome::Facts(ref actual)) => {
+                let (expected, actual) = (Outcome::values(expected), Outcome::values(actual));
+                let mut facts = expected.iter().chain(actual.iter()).map(|&(fact, _)| fact).collect::<Vec<_>>();
+                facts.sort_by_key(|fact| fact.name());
+                facts.dedup();
+
+                facts
+                    .into_iter()
+                    .filter_map(|fact| {
+                        let value = |values: &[(Fact, &'static str)]| {
+                            values.iter().find(|&&(other, _)| other == fact).map_or("missing", |&(_, value)| value)
+                        };
+                        let (expected, actual) = (value(&expected), value(&actual));
+
+                        if expected == actual {
+                            None
+                        } else {
+                            Some(format!("{}: expected {}, got {}", fact, expected, actual))
+                        }
+                    })
+                    .collect()
+            }
+            (expected, actual) if expected != actual => {
+                vec![format!("expected `{}`, got `{}`", expected.expectation(), actual.expectation())]
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Apply `query` to `kb`, returning what it gives if it's a query
+fn apply(kb: &mut KnowledgeBase, query: Query) -> Result<Option<Outcome>, String> {
+    match query {
+        Query::Rule(rule) => { kb.add_rule(rule).map_err(|e| e.to_string())?; }
+        Query::Given(given, constraints) => {
+            kb.set_given(given).map_err(|e| e.to_string())?;
+            kb.set_constraints(constraints);
+        }
+        Query::Find(find) => {
+            let outcome = match kb.ask(&find, &mut ()) {
+                Ok(verdicts) => {
+                    let mut facts = Facts::default();
+
+                    for verdict in verdicts {
+                        match verdict.truth {
+                            Truth::True => facts.yes.insert(verdict.fact),
+                            Truth::False => facts.no.insert(verdict.fact),
+                            Truth::Undetermined => facts.unknown.insert(verdict.fact),
+                        };
+                    }
+
+                    Outcome::Facts(facts)
+                }
+                Err(e) => Outcome::Error(e.to_string()),
+            };
+
+            return Ok(Some(outcome));
+        }
+        Query::Delete(rule) => { kb.remove_equivalent(&rule); }
+        Query::DeleteId(id) => { kb.remove_rule_by_id(id); }
+        Query::Describe(fact, info) => kb.describe(fact, info),
+        Query::Limit(limit) => {
+            let mut limits = *kb.limits();
+            limits.set(limit);
+            kb.set_limits(limits);
+        }
+        Query::Mode(mode) => kb.set_mode(mode),
+        Query::Dump | Query::Explain(..) | Query::Check | Query::Derive | Query::Empty => {}
+        query => return Err(format!("{:?} isn't supported in scenarios", query)),
+    }
+
+    Ok(None)
+}
+
+/// Run scenario at `path`, returning its text with expectations rewritten to actual outcomes
+/// and the mismatches found
+fn run(path: &Path) -> Result<(String, Vec<String>), String> {
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let lines = text.lines().collect::<Vec<_>>();
+
+    let mut kb = KnowledgeBase::new();
+    let (mut blessed, mut mismatches) = (String::new(), Vec::new());
+    let mut idx = 0;
+
+    while idx < lines.len() {
+        let line = lines[idx];
+        blessed.push_str(line);
+        blessed.push('\n');
+
+        let query = parser::query(line).map_err(|e| format!("{}: {}", idx + 1, e))?;
+        idx += 1;
+
+        let actual = match apply(&mut kb, query).map_err(|e| format!("{}: {}", idx, e))? {
+            Some(actual) => actual,
+            None => continue,
+        };
+
+        match lines.get(idx).and_then(|next| next.strip_prefix(EXPECT)) {
+            Some(next) => {
+                let expected = Outcome::parse(next).map_err(|e| format!("{}: {}", idx + 1, e))?;
+
+                for mismatch in expected.diff(&actual) {
+                    mismatches.push(format!("{}: {}: {}", idx, line.trim(), mismatch));
+                }
+
+                idx += 1;
+            }
+            None => mismatches.push(format!("{}: {}: no expectation", idx, line.trim())),
+        }
+
+        blessed.push_str(&actual.expectation());
+        blessed.push('\n');
+    }
+
+    Ok((blessed, mismatches))
+}
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let bless = args.iter().any(|arg| arg == "--bless");
+    let filters = args.iter().filter(|arg| !arg.starts_with('-')).collect::<Vec<_>>();
+
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("scenarios");
+    let mut paths = fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("{}: {}", dir.display(), e))
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    let (mut passed, mut failed) = (0, 0);
+
+    for path in paths.iter() {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if !filters.is_empty() && !filters.iter().any(|filter| name.contains(filter.as_str())) {
+            continue;
+        }
+
+        let result = run(path).and_then(|(blessed, mismatches)| {
+            if bless {
+                fs::write(path, blessed).map_err(|e| e.to_string())?;
+                Ok(Vec::new())
+            } else {
+                Ok(mismatches)
+            }
+        });
+
+        match result {
+            Ok(ref mismatches) if mismatches.is_empty() => {
+                println!("scenario {} ... {}", name, if bless { "blessed" } else { "ok" });
+                passed += 1;
+            }
+            Ok(mismatches) => {
+                println!("scenario {} ... FAILED", name);
+                for mismatch in mismatches {
+                    println!("    {}:{}", name, mismatch);
+                }
+                failed += 1;
+            }
+            Err(e) => {
+                println!("scenario {} ... FAILED", name);
+                println!("    {}:{}", name, e);
+                failed += 1;
+            }
+        }
+    }
+
+    println!();
+    println!("test result: {}. {} passed; {} failed", if failed == 0 { "ok" } else { "FAILED" }, passed, failed);
+
+    if failed > 0 {
+        eprintln!("run `cargo test --test scenarios -- --bless` to accept actual results");
+        process::exit(1);
+    }
+}